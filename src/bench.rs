@@ -0,0 +1,103 @@
+//! Frame time statistics for `--bench-frames`.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Share of the frames treated as warmup and left out of the summary: the
+/// first frames include pipeline and driver setup.
+const WARMUP_FRACTION: f64 = 0.1;
+
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    frame_times: Vec<Duration>,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, frame_time: Duration) {
+        self.frame_times.push(frame_time);
+    }
+
+    /// Statistics over the frames after the warmup window, `None` if none are left.
+    pub fn summary(&self) -> Option<Summary> {
+        let warmup = (self.frame_times.len() as f64 * WARMUP_FRACTION) as usize;
+        let mut times = self.frame_times[warmup..].to_vec();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort();
+        let total: Duration = times.iter().sum();
+        Some(Summary {
+            frames: times.len(),
+            warmup,
+            mean: total / times.len() as u32,
+            median: percentile(&times, 50.0),
+            p99: percentile(&times, 99.0),
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty `times`.
+fn percentile(times: &[Duration], percent: f64) -> Duration {
+    let rank = (percent / 100.0 * times.len() as f64).ceil() as usize;
+    times[rank.clamp(1, times.len()) - 1]
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    pub warmup: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p99: Duration,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} frames (after {} warmup): mean {:.3} ms, median {:.3} ms, p99 {:.3} ms, {:.1} frames/s",
+            self.frames,
+            self.warmup,
+            ms(self.mean),
+            ms(self.median),
+            ms(self.p99),
+            1.0 / self.mean.as_secs_f64(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(millis: impl IntoIterator<Item = u64>) -> FrameStats {
+        let mut stats = FrameStats::default();
+        for ms in millis {
+            stats.record(Duration::from_millis(ms));
+        }
+        stats
+    }
+
+    #[test]
+    fn warmup_frames_are_excluded() {
+        // The slow first frame falls in the warmup window
+        let summary = stats([500].into_iter().chain([10; 9])).summary().unwrap();
+        assert_eq!(summary.warmup, 1);
+        assert_eq!(summary.frames, 9);
+        assert_eq!(summary.mean, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let summary = stats(1..=100).summary().unwrap();
+        assert_eq!(summary.median, Duration::from_millis(55));
+        assert_eq!(summary.p99, Duration::from_millis(100));
+        assert_eq!(percentile(&[Duration::from_millis(7)], 99.0), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn empty_run_has_no_summary() {
+        assert_eq!(stats([]).summary(), None);
+    }
+}
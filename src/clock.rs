@@ -0,0 +1,57 @@
+//! Animation time, kept apart from the wall clock so animations can be
+//! slowed down, frozen or reversed while input stays as responsive as ever.
+
+/// Scales `Q` and `E` step through
+const STEPS: [f32; 10] = [-2.0, -1.0, -0.5, -0.25, 0.0, 0.25, 0.5, 1.0, 2.0, 4.0];
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationClock {
+    /// Animation seconds per real second; 0 freezes, negative runs backwards
+    pub scale: f32,
+}
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        Self { scale: 1.0 }
+    }
+}
+
+impl AnimationClock {
+    /// Animation time passed during `dt` real seconds.
+    pub fn delta(&self, dt: f32) -> f32 {
+        dt * self.scale
+    }
+
+    /// Moves to the next step above (`faster`) or below the current scale,
+    /// staying put at either end.
+    pub fn step(&mut self, faster: bool) {
+        let next = if faster {
+            STEPS.into_iter().find(|&step| step > self.scale)
+        } else {
+            STEPS.into_iter().rev().find(|&step| step < self.scale)
+        };
+        if let Some(scale) = next {
+            self.scale = scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_pass_through_freeze_into_reverse() {
+        let mut clock = AnimationClock { scale: 0.3 };
+        clock.step(false);
+        assert_eq!(clock.scale, 0.25);
+        clock.step(false);
+        assert_eq!(clock.delta(0.1), 0.0);
+        clock.step(false);
+        assert_eq!(clock.delta(0.1), -0.025);
+        for _ in 0..10 {
+            clock.step(true);
+        }
+        assert_eq!(clock.scale, 4.0);
+    }
+}
@@ -0,0 +1,89 @@
+//! Surface format and shader output encoding for each `ColorSpace` mode.
+//! The shaders compute linear colors; whether they reach the display
+//! sRGB-encoded depends on the target format and on the mode:
+//!
+//! | mode     | sRGB format offered                    | only linear formats        |
+//! |----------|----------------------------------------|----------------------------|
+//! | `auto`   | preferred format, encoded by the       | linear format, the shader  |
+//! |          | hardware or the shader as needed       | encodes                    |
+//! | `srgb`   | sRGB format, the hardware encodes      | linear format, the shader  |
+//! |          |                                        | encodes                    |
+//! | `linear` | a linear format if also offered, else  | linear format, no encoding |
+//! |          | sRGB with the shader decoding first    |                            |
+//!
+//! With `auto` and `srgb` the output is sRGB-encoded either way, by the
+//! hardware or by the shader, so colors match across machines; `linear`
+//! writes the shader's colors to the display unencoded.
+
+use crate::config::ColorSpace;
+
+/// Conversion the gallery shaders apply to their final color, matching the
+/// `output_encoding` values in shader.wgsl.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputEncoding {
+    /// The target format does what the mode needs
+    #[default]
+    None = 0,
+    /// Encode to sRGB for a linear target
+    EncodeSrgb = 1,
+    /// Decode from sRGB, so an sRGB target's encoding cancels out
+    DecodeSrgb = 2,
+}
+
+impl OutputEncoding {
+    /// The shaders' conversion of one color channel, for colors that don't
+    /// go through them, like the clear color.
+    pub fn apply(self, channel: f32) -> f32 {
+        let c = channel.clamp(0.0, 1.0);
+        match self {
+            OutputEncoding::None => channel,
+            OutputEncoding::EncodeSrgb if c <= 0.0031308 => c * 12.92,
+            OutputEncoding::EncodeSrgb => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+            OutputEncoding::DecodeSrgb if c <= 0.04045 => c / 12.92,
+            OutputEncoding::DecodeSrgb => ((c + 0.055) / 1.055).powf(2.4),
+        }
+    }
+}
+
+/// Picks the target format among `formats`, in order of preference, and the
+/// encoding the shaders need for it under `mode`.
+pub fn choose(formats: &[wgpu::TextureFormat], mode: ColorSpace) -> (wgpu::TextureFormat, OutputEncoding) {
+    let preferred = formats[0];
+    let format = match mode {
+        ColorSpace::Auto => preferred,
+        ColorSpace::ForceSrgb => formats.iter().copied().find(|format| format.is_srgb()).unwrap_or(preferred),
+        ColorSpace::ForceLinear => formats.iter().copied().find(|format| !format.is_srgb()).unwrap_or(preferred),
+    };
+    let encoding = match (mode, format.is_srgb()) {
+        (ColorSpace::Auto | ColorSpace::ForceSrgb, false) => OutputEncoding::EncodeSrgb,
+        (ColorSpace::ForceLinear, true) => OutputEncoding::DecodeSrgb,
+        _ => OutputEncoding::None,
+    };
+    (format, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb};
+
+    #[test]
+    fn every_mode_gets_its_encoding_from_either_format() {
+        let both = [Bgra8Unorm, Bgra8UnormSrgb];
+        assert_eq!(choose(&both, ColorSpace::Auto), (Bgra8Unorm, OutputEncoding::EncodeSrgb));
+        assert_eq!(choose(&both, ColorSpace::ForceSrgb), (Bgra8UnormSrgb, OutputEncoding::None));
+        assert_eq!(choose(&both, ColorSpace::ForceLinear), (Bgra8Unorm, OutputEncoding::None));
+        assert_eq!(choose(&[Bgra8UnormSrgb], ColorSpace::Auto), (Bgra8UnormSrgb, OutputEncoding::None));
+        assert_eq!(choose(&[Bgra8UnormSrgb], ColorSpace::ForceLinear), (Bgra8UnormSrgb, OutputEncoding::DecodeSrgb));
+        assert_eq!(choose(&[Bgra8Unorm], ColorSpace::ForceSrgb), (Bgra8Unorm, OutputEncoding::EncodeSrgb));
+    }
+
+    #[test]
+    fn decoding_undoes_encoding() {
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            let encoded = OutputEncoding::EncodeSrgb.apply(value);
+            assert!((OutputEncoding::DecodeSrgb.apply(encoded) - value).abs() < 1e-5);
+        }
+        assert!((OutputEncoding::EncodeSrgb.apply(0.214) - 0.5).abs() < 1e-3);
+    }
+}
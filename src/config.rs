@@ -0,0 +1,576 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::scene::ShadingModel;
+
+/// Runtime options, parsed from the command line.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// OBJ or STL files to show; the built-in cube is used when empty.
+    pub models: Vec<PathBuf>,
+    /// Start with the most conservative settings, see `apply_safe_mode`.
+    pub safe_mode: bool,
+    /// What to show when no models are given.
+    pub start: StartScene,
+    /// `(child, parent)` model indices: the child's coordinates become
+    /// relative to the parent, e.g. for parts of an articulated model.
+    pub parents: Vec<(usize, usize)>,
+    /// Model indices to draw without back-face culling.
+    pub double_sided: Vec<usize>,
+    /// `(model, shading)`: the model is drawn with a fixed shading model
+    /// instead of the active gallery shader.
+    pub shading: Vec<(usize, ShadingModel)>,
+    /// `(model, color)`: the model's base color is multiplied by the color.
+    pub tints: Vec<(usize, [f32; 3])>,
+    /// Query vertex/fragment invocation counts each frame and print them.
+    /// Only takes effect when the adapter supports `PIPELINE_STATISTICS_QUERY`.
+    pub pipeline_stats: bool,
+    /// How dragging with the left mouse button rotates the view.
+    pub camera_mode: CameraMode,
+    /// Reverse the vertical direction of mouse look.
+    pub invert_y: bool,
+    /// Scale the scroll zoom step by the distance to the surface at the
+    /// window center instead of moving a fixed distance.
+    pub smart_speed: bool,
+    /// Surface format preference and shader output encoding.
+    pub color_space: ColorSpace,
+    /// Acceleration and friction of inertial scroll movement, both per
+    /// second; instant movement when `None`.
+    pub inertia: Option<(f32, f32)>,
+    /// What the color target starts from in each render pass.
+    pub color_load: ColorLoad,
+    /// What the depth buffer starts from in each render pass, independently
+    /// of the color target.
+    pub depth_load: DepthLoad,
+    /// Alpha of the clear color, 1 when `None`; below 1 the desktop shows
+    /// through with a non-opaque `alpha_mode`.
+    pub clear_alpha: Option<f32>,
+    /// Requested window compositing mode. Checked against the surface at
+    /// startup and replaced by the mode actually in use.
+    pub alpha_mode: Option<wgpu::CompositeAlphaMode>,
+    /// Start with motion blur on, blending in the previous frame with this weight (0-1).
+    pub motion_blur: Option<f32>,
+    /// Start with the camera and frame time overlay shown.
+    pub hud: bool,
+    /// Start with depth of field on, focused on the framed objects.
+    pub depth_of_field: bool,
+    /// Offset the projection by a sub-pixel Halton sample every frame (TAA groundwork).
+    pub jitter: bool,
+    /// Clamp depth instead of clipping geometry against the near/far planes.
+    /// Useful for shadow-map passes (casters behind the light's near plane
+    /// still write depth) and skyboxes drawn at the far plane. Needs
+    /// `DEPTH_CLIP_CONTROL`.
+    pub unclipped_depth: bool,
+    /// Rasterize every pixel a triangle touches at all, not just those whose
+    /// center it covers, for coverage and voxelization experiments. Needs
+    /// `CONSERVATIVE_RASTERIZATION`.
+    pub conservative: bool,
+    /// Requested depth buffer format (WebGPU name, e.g. `depth24plus-stencil8`).
+    /// Checked against the adapter at startup and replaced by the format
+    /// actually in use.
+    pub depth_format: Option<wgpu::TextureFormat>,
+    /// Requested MSAA sample count. Checked against the adapter at startup
+    /// and replaced by the count actually in use.
+    pub msaa: Option<u32>,
+    /// Flip imported triangles whose winding disagrees with their normals.
+    pub fix_winding: bool,
+    /// Show how many fragments each pixel gets as a heat map instead of the
+    /// shaded scene.
+    pub overdraw: bool,
+    /// Which overlay lines are hidden behind the scene's surfaces instead of
+    /// drawn on top of everything.
+    pub overlay_depth_test: OverlayDepthTest,
+    /// Bounds tested against the view frustum.
+    pub frustum_culling: FrustumCulling,
+    /// Skip drawing objects whose bounds were hidden behind other objects
+    /// in the previous frame.
+    pub occlusion_culling: bool,
+    /// Start with the crosshair at the window center shown.
+    pub crosshair: bool,
+    /// Length of each crosshair arm in pixels.
+    pub crosshair_size: Option<f32>,
+    pub crosshair_color: Option<[f32; 3]>,
+    /// Wireframe edge colors of front- and back-facing triangles.
+    pub wireframe_front: Option<[f32; 3]>,
+    pub wireframe_back: Option<[f32; 3]>,
+    /// Start with the procedural checker/noise material on.
+    pub procedural: bool,
+    /// Procedural material checker cells per world unit.
+    pub procedural_scale: Option<f32>,
+    /// Seed of the procedural material's noise.
+    pub procedural_seed: Option<u32>,
+    /// The procedural material's two checker colors.
+    pub procedural_colors: Option<[[f32; 3]; 2]>,
+    /// Add a row of spheres that switch to coarser meshes as the camera moves away.
+    pub lod_demo: bool,
+    /// Base color texture (PNG, JPEG or binary PPM), loaded in the background
+    /// and shown by the textured shader.
+    pub texture: Option<PathBuf>,
+    /// Height map (PNG, JPEG or PPM, red channel) that displaces vertices along their normals.
+    pub displacement: Option<PathBuf>,
+    /// Anisotropic filtering level (1-16) for the base color texture.
+    pub anisotropy: Option<u16>,
+    /// Write all camera and toggle input to this file, for bug reports.
+    pub record: Option<PathBuf>,
+    /// Replay input recorded with `--record` instead of taking live input.
+    pub replay: Option<PathBuf>,
+    /// Append every frame's start time and duration to this CSV file.
+    pub trace_csv: Option<PathBuf>,
+    /// Index of the adapter to use in the list printed at startup, from
+    /// `--adapter` or the `BLINK_ADAPTER` environment variable. The default
+    /// adapter is used if it is out of range.
+    pub adapter: Option<usize>,
+    /// Number of per-frame uniform slots cycled through, so a frame's upload
+    /// doesn't wait on a frame the GPU is still drawing.
+    pub uniform_ring: Option<usize>,
+    /// Render one frame of the cube offscreen, check it and print the
+    /// adapter's capabilities, then exit with 0 on success and 1 on failure.
+    pub selftest: bool,
+    /// Render this many frames offscreen without a window, print frame time
+    /// statistics and exit.
+    pub bench_frames: Option<u32>,
+    /// Render a 360° panorama from the starting view to this PNG file without
+    /// a window and exit.
+    pub panorama: Option<PathBuf>,
+    /// Idle time after which the camera starts orbiting the scene.
+    pub showcase: Option<Duration>,
+    /// Degrees per second the camera keeps orbiting the scene, input or not.
+    pub spin: Option<f32>,
+    /// Starting speed of animations, 1 when `None`.
+    pub time_scale: Option<f32>,
+    /// Frame rate cap; uncapped (beyond Fifo's own vsync) when `None`.
+    pub max_fps: Option<FrameCap>,
+}
+
+/// Built-in scene shown without models; the simpler ones help tell geometry
+/// problems apart from surface and presentation problems.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StartScene {
+    /// The embedded sample model
+    #[default]
+    Sample,
+    Cube,
+    /// A single flat triangle
+    Triangle,
+    /// Nothing but the clear color
+    Empty,
+}
+
+/// Shape of the bounds objects are frustum culled by.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrustumCulling {
+    /// World-space box around the transformed object bounds: tighter, six corner picks per plane
+    #[default]
+    Box,
+    /// Bounding sphere: a single dot product per plane, but looser around long or flat meshes
+    Sphere,
+}
+
+/// Depth testing per world-space overlay; the screen-space crosshair is
+/// always on top.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverlayDepthTest {
+    /// The line between two measured points
+    pub measurement: bool,
+    /// The outline of the frozen culling frustum
+    pub frustum: bool,
+}
+
+/// Mapping from mouse drags to camera rotation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CameraMode {
+    /// Horizontal motion turns about the vertical axis, vertical motion about the horizontal one
+    #[default]
+    Look,
+    /// The cursor drags a virtual ball in front of the scene
+    Trackball,
+}
+
+/// How shader colors are brought into the display's color space; see
+/// color_space.rs for the format x mode matrix.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorSpace {
+    /// Keep the preferred surface format and sRGB-encode for it
+    #[default]
+    Auto,
+    /// Write linear values unencoded, preferring a linear surface format
+    ForceLinear,
+    /// Prefer an sRGB surface format, encoding in the shader without one
+    ForceSrgb,
+}
+
+pub const DEFAULT_CLEAR_COLOR: [f32; 3] = [0.1, 0.2, 0.3];
+
+/// Starting contents of the color target in a render pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorLoad {
+    Clear([f32; 3]),
+    /// Keep what earlier passes drew this frame, to composite several passes
+    Load,
+}
+
+impl Default for ColorLoad {
+    fn default() -> Self {
+        ColorLoad::Clear(DEFAULT_CLEAR_COLOR)
+    }
+}
+
+/// Starting contents of the depth (and stencil) buffer in a render pass.
+/// With `ColorLoad::Load`, clearing depth lets a later pass draw over the
+/// earlier ones regardless of their depth, e.g. an always-on-top gizmo;
+/// loading it makes the passes occlude each other like a single one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DepthLoad {
+    #[default]
+    Clear,
+    /// Keep the depth earlier passes wrote this frame; needs `ColorLoad::Load`
+    Load,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameCap {
+    Fps(f32),
+    /// The refresh rate of the monitor the window starts on
+    Monitor,
+}
+
+impl Config {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut config = Config::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--start" => match args.next().as_deref() {
+                    Some("sample") => config.start = StartScene::Sample,
+                    Some("cube") => config.start = StartScene::Cube,
+                    Some("triangle") => config.start = StartScene::Triangle,
+                    Some("empty") => config.start = StartScene::Empty,
+                    _ => eprintln!("--start expects sample, cube, triangle or empty"),
+                },
+                "--parent" => match args.next().as_deref().and_then(parse_parent) {
+                    Some(link) => config.parents.push(link),
+                    None => eprintln!("--parent expects CHILD:PARENT model indices"),
+                },
+                "--double-sided" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(index) => config.double_sided.push(index),
+                    None => eprintln!("--double-sided expects a model index"),
+                },
+                "--shading" => match args.next().as_deref().and_then(parse_shading) {
+                    Some(shading) => config.shading.push(shading),
+                    None => eprintln!("--shading expects INDEX:MODEL with a model of unlit, lambert, blinn-phong or normals"),
+                },
+                "--tint" => match args.next().as_deref().and_then(parse_tint) {
+                    Some(tint) => config.tints.push(tint),
+                    None => eprintln!("--tint expects INDEX:R,G,B with components from 0 to 1"),
+                },
+                "--clear" => match args.next().as_deref() {
+                    Some("load") => config.color_load = ColorLoad::Load,
+                    Some(value) => match parse_color(value) {
+                        Some(color) => config.color_load = ColorLoad::Clear(color),
+                        None => eprintln!("--clear expects an R,G,B color with components from 0 to 1, or \"load\""),
+                    },
+                    None => eprintln!("--clear expects an R,G,B color or \"load\""),
+                },
+                "--depth-load" => match args.next().as_deref() {
+                    Some("clear") => config.depth_load = DepthLoad::Clear,
+                    Some("load") => config.depth_load = DepthLoad::Load,
+                    _ => eprintln!("--depth-load expects clear or load"),
+                },
+                "--motion-blur" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(strength @ 0.0..1.0) => config.motion_blur = Some(strength),
+                    _ => eprintln!("--motion-blur expects a strength of at least 0 and below 1"),
+                },
+                "--depth-of-field" => config.depth_of_field = true,
+                "--hud" => config.hud = true,
+                "--clear-alpha" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(alpha @ 0.0..=1.0) => config.clear_alpha = Some(alpha),
+                    _ => eprintln!("--clear-alpha expects an alpha from 0 to 1"),
+                },
+                "--alpha-mode" => match args.next().as_deref().and_then(parse_alpha_mode) {
+                    Some(mode) => config.alpha_mode = Some(mode),
+                    None => eprintln!("--alpha-mode expects one of opaque, premultiplied, postmultiplied, inherit"),
+                },
+                "--pipeline-stats" => config.pipeline_stats = true,
+                "--jitter" => config.jitter = true,
+                "--invert-y" => config.invert_y = true,
+                "--smart-speed" => config.smart_speed = true,
+                "--color-space" => match args.next().as_deref() {
+                    Some("auto") => config.color_space = ColorSpace::Auto,
+                    Some("linear") => config.color_space = ColorSpace::ForceLinear,
+                    Some("srgb") => config.color_space = ColorSpace::ForceSrgb,
+                    _ => eprintln!("--color-space expects auto, linear or srgb"),
+                },
+                "--inertia" => match args.next().as_deref().and_then(parse_inertia) {
+                    Some(inertia) => config.inertia = Some(inertia),
+                    None => eprintln!("--inertia expects ACCELERATION,FRICTION, both at least 0 (friction may be inf)"),
+                },
+                "--overlay-depth-test" => match args.next().as_deref().and_then(parse_overlay_depth_test) {
+                    Some(depth_test) => config.overlay_depth_test = depth_test,
+                    None => eprintln!("--overlay-depth-test expects a comma-separated list of measurement and frustum"),
+                },
+                "--camera-mode" => match args.next().as_deref() {
+                    Some("look") => config.camera_mode = CameraMode::Look,
+                    Some("trackball") => config.camera_mode = CameraMode::Trackball,
+                    _ => eprintln!("--camera-mode expects look or trackball"),
+                },
+                "--unclipped-depth" => config.unclipped_depth = true,
+                "--conservative" => config.conservative = true,
+                "--lod-demo" => config.lod_demo = true,
+                "--occlusion-culling" => config.occlusion_culling = true,
+                "--fix-winding" => config.fix_winding = true,
+                "--safe-mode" => config.safe_mode = true,
+                "--overdraw" => config.overdraw = true,
+                "--frustum-culling" => match args.next().as_deref() {
+                    Some("box") => config.frustum_culling = FrustumCulling::Box,
+                    Some("sphere") => config.frustum_culling = FrustumCulling::Sphere,
+                    _ => eprintln!("--frustum-culling expects box or sphere"),
+                },
+                "--depth-format" => match args.next().as_deref().and_then(parse_depth_format) {
+                    Some(format) => config.depth_format = Some(format),
+                    None => eprintln!(
+                        "--depth-format expects one of depth16unorm, depth24plus, depth24plus-stencil8, depth32float, depth32float-stencil8"
+                    ),
+                },
+                "--wireframe-front" | "--wireframe-back" => match args.next().as_deref().and_then(parse_color) {
+                    Some(color) if arg == "--wireframe-front" => config.wireframe_front = Some(color),
+                    Some(color) => config.wireframe_back = Some(color),
+                    None => eprintln!("{arg} expects an R,G,B color with components from 0 to 1"),
+                },
+                "--crosshair" => config.crosshair = true,
+                "--crosshair-size" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(size) if size > 0.0 => config.crosshair_size = Some(size),
+                    _ => eprintln!("--crosshair-size expects a positive length in pixels"),
+                },
+                "--crosshair-color" => match args.next().as_deref().and_then(parse_color) {
+                    Some(color) => config.crosshair_color = Some(color),
+                    None => eprintln!("--crosshair-color expects an R,G,B color with components from 0 to 1"),
+                },
+                "--procedural" => config.procedural = true,
+                "--procedural-scale" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(scale) if scale > 0.0 => config.procedural_scale = Some(scale),
+                    _ => eprintln!("--procedural-scale expects a positive number of cells per unit"),
+                },
+                "--procedural-seed" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => config.procedural_seed = Some(seed),
+                    None => eprintln!("--procedural-seed expects a non-negative integer"),
+                },
+                "--procedural-colors" => match args.next().as_deref().and_then(parse_color_pair) {
+                    Some(colors) => config.procedural_colors = Some(colors),
+                    None => eprintln!("--procedural-colors expects two R,G,B colors separated by a colon"),
+                },
+                "--msaa" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(samples @ (1 | 2 | 4 | 8 | 16)) => config.msaa = Some(samples),
+                    _ => eprintln!("--msaa expects a sample count of 1, 2, 4, 8 or 16"),
+                },
+                "--texture" => match args.next() {
+                    Some(path) => config.texture = Some(PathBuf::from(path)),
+                    None => eprintln!("--texture expects an image file path"),
+                },
+                "--displacement" => match args.next() {
+                    Some(path) => config.displacement = Some(PathBuf::from(path)),
+                    None => eprintln!("--displacement expects an image file path"),
+                },
+                "--anisotropy" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(level @ 1..=16) => config.anisotropy = Some(level),
+                    _ => eprintln!("--anisotropy expects a level from 1 to 16"),
+                },
+                "--record" | "--replay" => match args.next() {
+                    Some(path) if arg == "--record" => config.record = Some(PathBuf::from(path)),
+                    Some(path) => config.replay = Some(PathBuf::from(path)),
+                    None => eprintln!("{arg} expects an input log path"),
+                },
+                "--trace-csv" => match args.next() {
+                    Some(path) => config.trace_csv = Some(PathBuf::from(path)),
+                    None => eprintln!("--trace-csv expects an output CSV path"),
+                },
+                "--adapter" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(index) => config.adapter = Some(index),
+                    None => eprintln!("--adapter expects an adapter index"),
+                },
+                "--uniform-ring" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(slots @ 1..) => config.uniform_ring = Some(slots),
+                    _ => eprintln!("--uniform-ring expects a positive slot count"),
+                },
+                "--selftest" => config.selftest = true,
+                "--bench-frames" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(frames @ 1..) => config.bench_frames = Some(frames),
+                    _ => eprintln!("--bench-frames expects a positive frame count"),
+                },
+                "--panorama" => match args.next() {
+                    Some(path) => config.panorama = Some(PathBuf::from(path)),
+                    None => eprintln!("--panorama expects an output PNG path"),
+                },
+                "--showcase" => match args.next().and_then(|value| value.parse().ok()).and_then(|s| Duration::try_from_secs_f32(s).ok()) {
+                    Some(timeout) => config.showcase = Some(timeout),
+                    None => eprintln!("--showcase expects an idle time in seconds"),
+                },
+                "--spin" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(rate) => config.spin = Some(rate),
+                    None => eprintln!("--spin expects a rate in degrees per second"),
+                },
+                "--time-scale" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(scale) if f32::is_finite(scale) => config.time_scale = Some(scale),
+                    _ => eprintln!("--time-scale expects a speed factor, e.g. 0.5 or -1"),
+                },
+                "--max-fps" => match args.next().as_deref().and_then(parse_frame_cap) {
+                    Some(cap) => config.max_fps = Some(cap),
+                    None => eprintln!("--max-fps expects a positive frame rate or \"monitor\""),
+                },
+                _ if arg.starts_with("--") => eprintln!("Ignoring unknown argument: {arg}"),
+                _ => config.models.push(PathBuf::from(arg)),
+            }
+        }
+        // Depth kept from a pass whose colors were cleared away would hide
+        // parts of the next pass behind surfaces that are no longer shown
+        if config.depth_load == DepthLoad::Load && config.color_load != ColorLoad::Load {
+            eprintln!("--depth-load load needs --clear load; clearing depth instead");
+            config.depth_load = DepthLoad::Clear;
+        }
+        if config.safe_mode {
+            config.apply_safe_mode();
+        }
+        if config.adapter.is_none()
+            && let Ok(value) = std::env::var("BLINK_ADAPTER")
+        {
+            match value.parse() {
+                Ok(index) => config.adapter = Some(index),
+                Err(_) => eprintln!("BLINK_ADAPTER expects an adapter index"),
+            }
+        }
+        config
+    }
+
+    /// Replaces everything that depends on the GPU or driver with the most
+    /// conservative choice: the GL backend preferring the fallback adapter,
+    /// no optional features, no multisampling, default formats and modes,
+    /// and the plain cube instead of models and textures. Frames are
+    /// presented with Fifo in any mode.
+    pub fn apply_safe_mode(&mut self) {
+        self.safe_mode = true;
+        self.models.clear();
+        self.start = StartScene::Cube;
+        self.texture = None;
+        self.displacement = None;
+        self.lod_demo = false;
+        self.msaa = Some(1);
+        self.depth_format = None;
+        self.alpha_mode = None;
+        self.adapter = None;
+        self.anisotropy = None;
+        self.pipeline_stats = false;
+        self.unclipped_depth = false;
+        self.conservative = false;
+        self.occlusion_culling = false;
+        self.motion_blur = None;
+        self.depth_of_field = false;
+    }
+
+    /// Collects the optional device features the options need. Options whose
+    /// feature the adapter lacks are switched off with a warning, so the rest
+    /// of the app can rely on the flags.
+    pub fn negotiate_features(&mut self, available: wgpu::Features) -> wgpu::Features {
+        // Not tied to options: wireframe mode and triangle colors are offered
+        // whenever they're available
+        let offered = if self.safe_mode {
+            wgpu::Features::empty()
+        } else {
+            wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::SHADER_PRIMITIVE_INDEX
+        };
+        let mut required = available & offered;
+        let mut request = |enabled: &mut bool, feature: wgpu::Features, flag: &str| {
+            if !*enabled {
+                return;
+            }
+            if available.contains(feature) {
+                required |= feature;
+            } else {
+                println!("{flag} needs {feature:?}, which this adapter does not support; ignoring it");
+                *enabled = false;
+            }
+        };
+        request(&mut self.pipeline_stats, wgpu::Features::PIPELINE_STATISTICS_QUERY, "--pipeline-stats");
+        request(&mut self.unclipped_depth, wgpu::Features::DEPTH_CLIP_CONTROL, "--unclipped-depth");
+        request(&mut self.conservative, wgpu::Features::CONSERVATIVE_RASTERIZATION, "--conservative");
+        if let Some(format) = self.depth_format {
+            let mut enabled = true;
+            request(&mut enabled, format.required_features(), "--depth-format");
+            if !enabled {
+                self.depth_format = None;
+            }
+        }
+        required
+    }
+}
+
+fn parse_parent(value: &str) -> Option<(usize, usize)> {
+    let (child, parent) = value.split_once(':')?;
+    Some((child.parse().ok()?, parent.parse().ok()?))
+}
+
+fn parse_shading(value: &str) -> Option<(usize, ShadingModel)> {
+    let (index, name) = value.split_once(':')?;
+    let model = ShadingModel::ALL.into_iter().find(|model| model.name() == name)?;
+    Some((index.parse().ok()?, model))
+}
+
+fn parse_tint(value: &str) -> Option<(usize, [f32; 3])> {
+    let (index, color) = value.split_once(':')?;
+    Some((index.parse().ok()?, parse_color(color)?))
+}
+
+fn parse_depth_format(name: &str) -> Option<wgpu::TextureFormat> {
+    Some(match name {
+        "depth16unorm" => wgpu::TextureFormat::Depth16Unorm,
+        "depth24plus" => wgpu::TextureFormat::Depth24Plus,
+        "depth24plus-stencil8" => wgpu::TextureFormat::Depth24PlusStencil8,
+        "depth32float" => wgpu::TextureFormat::Depth32Float,
+        "depth32float-stencil8" => wgpu::TextureFormat::Depth32FloatStencil8,
+        _ => return None,
+    })
+}
+
+fn parse_alpha_mode(name: &str) -> Option<wgpu::CompositeAlphaMode> {
+    Some(match name {
+        "opaque" => wgpu::CompositeAlphaMode::Opaque,
+        "premultiplied" => wgpu::CompositeAlphaMode::PreMultiplied,
+        "postmultiplied" => wgpu::CompositeAlphaMode::PostMultiplied,
+        "inherit" => wgpu::CompositeAlphaMode::Inherit,
+        _ => return None,
+    })
+}
+
+fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let components: Vec<f32> = value.split(',').map(|c| c.trim().parse().ok()).collect::<Option<_>>()?;
+    let color: [f32; 3] = components.try_into().ok()?;
+    color.iter().all(|c| (0.0..=1.0).contains(c)).then_some(color)
+}
+
+fn parse_color_pair(value: &str) -> Option<[[f32; 3]; 2]> {
+    let (first, second) = value.split_once(':')?;
+    Some([parse_color(first)?, parse_color(second)?])
+}
+
+fn parse_overlay_depth_test(value: &str) -> Option<OverlayDepthTest> {
+    let mut depth_test = OverlayDepthTest::default();
+    for name in value.split(',') {
+        match name.trim() {
+            "measurement" => depth_test.measurement = true,
+            "frustum" => depth_test.frustum = true,
+            _ => return None,
+        }
+    }
+    Some(depth_test)
+}
+
+fn parse_inertia(value: &str) -> Option<(f32, f32)> {
+    let (acceleration, friction) = value.split_once(',')?;
+    let (acceleration, friction): (f32, f32) = (acceleration.trim().parse().ok()?, friction.trim().parse().ok()?);
+    (acceleration.is_finite() && acceleration >= 0.0 && friction >= 0.0).then_some((acceleration, friction))
+}
+
+fn parse_frame_cap(value: &str) -> Option<FrameCap> {
+    if value == "monitor" {
+        return Some(FrameCap::Monitor);
+    }
+    let fps: f32 = value.parse().ok()?;
+    (fps.is_finite() && fps > 0.0).then_some(FrameCap::Fps(fps))
+}
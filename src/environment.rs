@@ -0,0 +1,96 @@
+//! Procedural environment cubemap used as a reflection probe.
+
+use glam::Vec3;
+
+const FACE_SIZE: u32 = 64;
+const ZENITH: Vec3 = Vec3::new(0.15, 0.35, 0.75);
+const HORIZON: Vec3 = Vec3::new(0.8, 0.85, 0.9);
+const GROUND: Vec3 = Vec3::new(0.25, 0.2, 0.15);
+/// Matches `LIGHT_DIRECTION` in the lit shader so reflections show the sun where the light is
+const SUN_DIRECTION: Vec3 = Vec3::new(0.4, 0.8, 0.6);
+
+pub struct Environment {
+    _texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let size = wgpu::Extent3d { width: FACE_SIZE, height: FACE_SIZE, depth_or_array_layers: 6 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Environment Cubemap"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &sky_texels(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(FACE_SIZE * 4),
+                rows_per_image: Some(FACE_SIZE),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Environment Cubemap View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self { _texture: texture, view, sampler }
+    }
+}
+
+/// World-space direction through texel (u, v) of a cube face, with u and v in [-1, 1].
+/// Faces are in wgpu's layer order: +X, -X, +Y, -Y, +Z, -Z.
+fn face_direction(face: u32, u: f32, v: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+}
+
+fn sky_color(direction: Vec3) -> Vec3 {
+    let direction = direction.normalize();
+    let sky = if direction.y >= 0.0 {
+        HORIZON.lerp(ZENITH, direction.y.sqrt())
+    } else {
+        GROUND.lerp(HORIZON * 0.5, (1.0 + direction.y).powi(8))
+    };
+    let sun = direction.dot(SUN_DIRECTION.normalize()).max(0.0).powi(256);
+    sky + Vec3::splat(sun)
+}
+
+fn sky_texels() -> Vec<u8> {
+    let mut texels = Vec::with_capacity((FACE_SIZE * FACE_SIZE * 6 * 4) as usize);
+    for face in 0..6 {
+        for y in 0..FACE_SIZE {
+            for x in 0..FACE_SIZE {
+                let to_unit = |t: u32| (t as f32 + 0.5) / FACE_SIZE as f32 * 2.0 - 1.0;
+                // Stored as sRGB, so encode the linear color before quantizing
+                let color = sky_color(face_direction(face, to_unit(x), to_unit(y)))
+                    .clamp(Vec3::ZERO, Vec3::ONE)
+                    .powf(1.0 / 2.2);
+                texels.extend((color * 255.0).round().to_array().map(|c| c as u8));
+                texels.push(255);
+            }
+        }
+    }
+    texels
+}
@@ -0,0 +1,107 @@
+//! View frustum culling: objects whose bounds lie entirely outside the
+//! camera's view volume are not drawn. Bounds are either boxes, or spheres,
+//! which are cheaper to test but usually fit the mesh more loosely.
+
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::mesh::{Aabb, BoundingSphere};
+
+/// The six planes of a view volume, as `(normal, distance)` with unit normals
+/// pointing inwards.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix with wgpu's 0-1 depth range.
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let row = |i| view_proj.row(i);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| plane / plane.truncate().length());
+        Self { planes }
+    }
+
+    /// Whether any part of the box may be inside. Boxes near the frustum's
+    /// corners can pass without being visible, which only costs a draw.
+    pub fn intersects_aabb(&self, bounds: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // The corner furthest along the normal
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), bounds.max, bounds.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+
+    /// Whether any part of the sphere may be inside; one dot product per plane.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+}
+
+/// World-space corners of the view volume; bit 0 of the index selects
+/// right, bit 1 top and bit 2 the far plane.
+pub fn corners(view_proj: Mat4) -> [Vec3; 8] {
+    let inverse = view_proj.inverse();
+    std::array::from_fn(|i| {
+        let pick = |bit, lo: f32, hi: f32| if i & bit == 0 { lo } else { hi };
+        inverse.project_point3(Vec3::new(pick(1, -1.0, 1.0), pick(2, -1.0, 1.0), pick(4, 0.0, 1.0)))
+    })
+}
+
+/// The twelve edges of the view volume, as pairs of `corners()` indices.
+pub fn edges() -> impl Iterator<Item = (usize, usize)> {
+    (0..8usize).flat_map(|i| [1, 2, 4].into_iter().filter(move |bit| i & bit == 0).map(move |bit| (i, i | bit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Mat4 {
+        let projection = Mat4::perspective_rh(90.0_f32.to_radians(), 1.0, 0.1, 100.0);
+        projection * Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y)
+    }
+
+    fn unit_box(center: Vec3) -> Aabb {
+        Aabb { min: center - 0.5, max: center + 0.5 }
+    }
+
+    #[test]
+    fn boxes_outside_are_culled() {
+        let frustum = Frustum::from_view_proj(camera());
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, -10.0))));
+        // Straddling the left plane
+        assert!(frustum.intersects_aabb(&unit_box(Vec3::new(-10.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(-12.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, 10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box(Vec3::new(0.0, 0.0, -200.0))));
+    }
+
+    #[test]
+    fn spheres_outside_are_culled() {
+        let frustum = Frustum::from_view_proj(camera());
+        let sphere = |x, z| BoundingSphere { center: Vec3::new(x, 0.0, z), radius: 1.0 };
+        assert!(frustum.intersects_sphere(&sphere(0.0, -10.0)));
+        // Straddling the left plane, whose normal is at 45° to the X axis
+        assert!(frustum.intersects_sphere(&sphere(-11.0, -10.0)));
+        assert!(!frustum.intersects_sphere(&sphere(-11.5, -10.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 2.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, -102.0)));
+    }
+
+    #[test]
+    fn corners_span_near_and_far_planes() {
+        let corners = corners(camera());
+        assert!(corners[0].abs_diff_eq(Vec3::new(-0.1, -0.1, -0.1), 1e-4));
+        assert!(corners[7].abs_diff_eq(Vec3::new(100.0, 100.0, -100.0), 1e-1));
+        assert_eq!(edges().count(), 12);
+    }
+}
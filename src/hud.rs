@@ -0,0 +1,280 @@
+//! Debug overlay in the top-left corner: camera position, orientation, field
+//! of view and frame time, drawn as text with a built-in bitmap font.
+
+use std::time::Duration;
+
+use glam::{Quat, Vec3};
+
+use crate::texture::Image;
+
+const HUD_SHADER: &str = include_str!("shaders/hud.wgsl");
+
+/// Text grid of the overlay; longer lines are cut off
+const COLUMNS: u32 = 28;
+const LINES: u32 = 4;
+/// Glyph width and the cell each glyph sits in, in texels
+const GLYPH_WIDTH: u32 = 5;
+const CELL_WIDTH: u32 = 6;
+const CELL_HEIGHT: u32 = 9;
+/// Border around the text, in texels
+const PADDING: u32 = 2;
+const TEXTURE_WIDTH: u32 = COLUMNS * CELL_WIDTH + PADDING * 2;
+const TEXTURE_HEIGHT: u32 = LINES * CELL_HEIGHT + PADDING * 2;
+/// Screen pixels per texel, and the distance from the window corner in pixels
+const SCALE: u32 = 2;
+const MARGIN: u32 = 8;
+/// Premultiplied translucent black behind the text
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+
+/// 5x7 glyphs, one byte per row from the top, bit 4 the leftmost column.
+/// Only the characters the readout uses; anything else is drawn blank.
+const GLYPHS: &[(char, [u8; 7])] = &[
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('Y', [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04]),
+];
+
+/// The values shown, captured once per frame.
+#[derive(Debug)]
+pub struct Readout {
+    pub position: Vec3,
+    /// View rotation, as in `Camera::rotation`
+    pub rotation: Quat,
+    /// Vertical field of view in radians
+    pub fov: f32,
+    pub frame_time: Duration,
+}
+
+impl Readout {
+    pub fn lines(&self) -> [String; LINES as usize] {
+        // The view rotation turns the world; the camera looks down -Z of its inverse
+        let forward = self.rotation.inverse() * Vec3::NEG_Z;
+        let yaw = forward.x.atan2(-forward.z).to_degrees();
+        let pitch = forward.y.clamp(-1.0, 1.0).asin().to_degrees();
+        let Vec3 { x, y, z } = self.position;
+        [
+            format!("POS {x:.2} {y:.2} {z:.2}"),
+            format!("YAW {yaw:.1} PITCH {pitch:.1}"),
+            format!("FOV {:.1}", self.fov.to_degrees()),
+            format!("FRAME {:.2} MS", self.frame_time.as_secs_f64() * 1000.0),
+        ]
+    }
+}
+
+/// Draws `lines` into an image the size of the overlay: white text on `BACKGROUND`.
+fn rasterize(lines: &[String]) -> Image {
+    let mut pixels = BACKGROUND.repeat((TEXTURE_WIDTH * TEXTURE_HEIGHT) as usize);
+    for (row, line) in lines.iter().take(LINES as usize).enumerate() {
+        for (column, c) in line.chars().take(COLUMNS as usize).enumerate() {
+            let Some((_, glyph)) = GLYPHS.iter().find(|(g, _)| *g == c.to_ascii_uppercase()) else { continue };
+            for (gy, bits) in glyph.iter().enumerate() {
+                for gx in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - gx)) == 0 {
+                        continue;
+                    }
+                    let x = PADDING + column as u32 * CELL_WIDTH + gx;
+                    let y = PADDING + row as u32 * CELL_HEIGHT + gy as u32;
+                    let texel = ((y * TEXTURE_WIDTH + x) * 4) as usize;
+                    pixels[texel..texel + 4].copy_from_slice(&[255; 4]);
+                }
+            }
+        }
+    }
+    Image { width: TEXTURE_WIDTH, height: TEXTURE_HEIGHT, pixels }
+}
+
+pub struct Hud {
+    texture: wgpu::Texture,
+    /// Overlay rectangle in NDC, `(left, top, right, bottom)`
+    rect_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Hud {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HUD Texture"),
+            size: wgpu::Extent3d { width: TEXTURE_WIDTH, height: TEXTURE_HEIGHT, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let rect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("HUD Rect Buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Texels map to whole pixels, so nearest filtering keeps the glyphs crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor { label: Some("HUD Sampler"), ..Default::default() });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("hud_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("hud_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: rect_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&sampler) },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("hud"),
+            source: wgpu::ShaderSource::Wgsl(HUD_SHADER.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("HUD Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("HUD Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_hud",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_hud",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState { topology: wgpu::PrimitiveTopology::TriangleStrip, ..Default::default() },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self { texture, rect_buffer, bind_group, pipeline }
+    }
+
+    /// Records a pass drawing `readout` over the top-left corner of `view`,
+    /// a target of `width` by `height` pixels.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, view: &wgpu::TextureView, (width, height): (u32, u32), readout: &Readout) {
+        let image = rasterize(&readout.lines());
+        queue.write_texture(
+            self.texture.as_image_copy(),
+            &image.pixels,
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(image.width * 4), rows_per_image: None },
+            wgpu::Extent3d { width: image.width, height: image.height, depth_or_array_layers: 1 },
+        );
+        let to_ndc_x = |pixels: u32| pixels as f32 / width as f32 * 2.0 - 1.0;
+        let to_ndc_y = |pixels: u32| 1.0 - pixels as f32 / height as f32 * 2.0;
+        let rect = [
+            to_ndc_x(MARGIN),
+            to_ndc_y(MARGIN),
+            to_ndc_x(MARGIN + TEXTURE_WIDTH * SCALE),
+            to_ndc_y(MARGIN + TEXTURE_HEIGHT * SCALE),
+        ];
+        queue.write_buffer(&self.rect_buffer, 0, bytemuck::cast_slice(&rect));
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("HUD Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readout_formats_camera_state() {
+        let readout = Readout {
+            position: Vec3::new(1.0, -2.5, 10.0),
+            // Turns the world so the camera faces +X
+            rotation: Quat::from_rotation_y(90.0_f32.to_radians()),
+            fov: 45.0_f32.to_radians(),
+            frame_time: Duration::from_micros(16_667),
+        };
+        let lines = readout.lines();
+        assert_eq!(lines[0], "POS 1.00 -2.50 10.00");
+        assert_eq!(lines[1], "YAW 90.0 PITCH 0.0");
+        assert_eq!(lines[2], "FOV 45.0");
+        assert_eq!(lines[3], "FRAME 16.67 MS");
+    }
+
+    #[test]
+    fn glyphs_are_drawn_in_their_cells() {
+        let image = rasterize(&["-".to_string()]);
+        let texel = |x: u32, y: u32| &image.pixels[((y * TEXTURE_WIDTH + x) * 4) as usize..][..4];
+        // The dash is the middle row of the first cell
+        assert_eq!(texel(PADDING, PADDING + 3), [255; 4]);
+        assert_eq!(texel(PADDING, PADDING + 2), BACKGROUND);
+        assert_eq!(texel(PADDING + CELL_WIDTH, PADDING + 3), BACKGROUND);
+    }
+}
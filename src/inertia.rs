@@ -0,0 +1,88 @@
+//! Camera movement inertia: input accelerates a velocity instead of moving
+//! the camera directly, and friction slows it down again, so movement eases
+//! out rather than stopping dead.
+
+use glam::Vec3;
+
+/// Velocity state and tuning of inertial movement. With zero acceleration or
+/// infinite friction, input moves the camera instantly.
+#[derive(Clone, Copy, Debug)]
+pub struct Inertia {
+    /// Velocity gained per unit of input displacement, per second. Equal to
+    /// `friction`, the camera coasts as far as the input moved it instantly.
+    pub acceleration: f32,
+    /// Exponential decay rate of the velocity, per second
+    pub friction: f32,
+    velocity: Vec3,
+}
+
+impl Default for Inertia {
+    fn default() -> Self {
+        Self::new(0.0, f32::INFINITY)
+    }
+}
+
+impl Inertia {
+    pub fn new(acceleration: f32, friction: f32) -> Self {
+        Self { acceleration, friction, velocity: Vec3::ZERO }
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.acceleration == 0.0 || self.friction.is_infinite()
+    }
+
+    /// Feeds in a movement the input asks for and returns how far to move
+    /// right away: all of it when instant, nothing otherwise, as `advance`
+    /// applies it over the next frames.
+    pub fn push(&mut self, displacement: Vec3) -> Vec3 {
+        if self.is_instant() {
+            return displacement;
+        }
+        self.velocity += displacement * self.acceleration;
+        Vec3::ZERO
+    }
+
+    /// Movement over the `dt` seconds since the last frame, slowing down by
+    /// the friction meanwhile.
+    pub fn advance(&mut self, dt: f32) -> Vec3 {
+        if self.velocity == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+        // Integrated exactly: v(t) = v0 e^(-ft) covers v0 (1 - e^(-ft)) / f
+        let decay = (-self.friction * dt).exp();
+        let moved = if self.friction > 0.0 { self.velocity * (1.0 - decay) / self.friction } else { self.velocity * dt };
+        self.velocity *= decay;
+        if self.velocity.length_squared() < 1e-12 {
+            self.velocity = Vec3::ZERO;
+        }
+        moved
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = Vec3::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_coasts_after_input_stops() {
+        let mut inertia = Inertia::new(8.0, 8.0);
+        assert_eq!(inertia.push(Vec3::Z), Vec3::ZERO);
+        let frames: Vec<f32> = (0..120).map(|_| inertia.advance(1.0 / 60.0).z).collect();
+        // Still moving after the input, slower every frame
+        assert!(frames[10] > 0.0 && frames.windows(2).all(|pair| pair[1] <= pair[0]));
+        // Coasting ends where instant movement would have gone
+        assert!((frames.iter().sum::<f32>() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn zero_acceleration_or_infinite_friction_is_instant() {
+        for mut inertia in [Inertia::new(0.0, 5.0), Inertia::new(5.0, f32::INFINITY), Inertia::default()] {
+            assert_eq!(inertia.push(Vec3::X), Vec3::X);
+            assert_eq!(inertia.advance(0.1), Vec3::ZERO);
+        }
+    }
+}
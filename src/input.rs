@@ -0,0 +1,226 @@
+//! Recording and replaying the input events that drive the camera and
+//! toggles, for reproducing navigation bugs.
+//!
+//! Logs are JSON lines: an `input` entry per event and a `frame` entry per
+//! frame holding the time it advanced by. Replay is keyed on the frame
+//! number rather than the timestamp, and feeds the recorded frame times back,
+//! so inertia and timed motion match the recording however fast the machine
+//! renders.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+
+/// Keys `App::handle_key` reacts to; other keys are not recorded.
+const RECORDED_KEYS: &[KeyCode] = &[
+    KeyCode::Tab,
+    KeyCode::KeyJ,
+    KeyCode::KeyY,
+    KeyCode::KeyC,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::KeyL,
+    KeyCode::KeyK,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyI,
+    KeyCode::KeyM,
+    KeyCode::KeyB,
+    KeyCode::KeyD,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyN,
+    KeyCode::KeyS,
+    KeyCode::KeyQ,
+    KeyCode::KeyE,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyX,
+    KeyCode::KeyZ,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Semicolon,
+    KeyCode::Quote,
+    KeyCode::KeyW,
+    KeyCode::KeyT,
+    KeyCode::KeyF,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::KeyR,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::Digit0,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::F3,
+    KeyCode::F5,
+    KeyCode::F12,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputEvent {
+    /// Left mouse button pressed or released
+    MouseButton { pressed: bool },
+    CursorMoved(Vec2),
+    Key(KeyCode),
+    /// Wheel movement in lines
+    Scroll(f32),
+    /// Raw mouse motion from the device, in unspecified units
+    MouseMotion(Vec2),
+}
+
+impl InputEvent {
+    /// Whether the event is recorded; keys `App::handle_key` ignores aren't.
+    fn is_recorded(&self) -> bool {
+        match self {
+            InputEvent::Key(key) => RECORDED_KEYS.contains(key),
+            _ => true,
+        }
+    }
+}
+
+/// One line of a log.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    /// Frame `frame` advanced time by `dt` seconds
+    Frame { frame: u32, dt: f64 },
+    /// `event` arrived before frame `frame`, `seconds` after recording
+    /// started; the timestamp is informational
+    Input { frame: u32, seconds: f64, event: InputEvent },
+}
+
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl EventRecorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?), start: Instant::now() })
+    }
+
+    pub fn record(&mut self, frame: u32, event: &InputEvent) {
+        if !event.is_recorded() {
+            return;
+        }
+        let seconds = self.start.elapsed().as_secs_f64();
+        self.write(&Entry::Input { frame, seconds, event: *event });
+    }
+
+    /// Records the frame time `frame` advances by, for replays to reuse.
+    pub fn record_frame(&mut self, frame: u32, dt: Duration) {
+        self.write(&Entry::Frame { frame, dt: dt.as_secs_f64() });
+    }
+
+    fn write(&mut self, entry: &Entry) {
+        let result = serde_json::to_writer(&mut self.writer, entry).map_err(std::io::Error::from);
+        if let Err(e) = result.and_then(|()| writeln!(self.writer)) {
+            eprintln!("Failed to record input event: {e}");
+        }
+    }
+}
+
+/// A recorded log, fed back one frame at a time.
+pub struct EventReplay {
+    events: VecDeque<(u32, InputEvent)>,
+    frame_times: VecDeque<(u32, Duration)>,
+}
+
+impl EventReplay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::parse(&source).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut replay = Self { events: VecDeque::new(), frame_times: VecDeque::new() };
+        for (number, line) in source.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = |e: &dyn std::fmt::Display| format!("line {}: invalid entry ({e})", number + 1);
+            match serde_json::from_str(line).map_err(|e| invalid(&e))? {
+                Entry::Frame { frame, dt } => {
+                    let dt = Duration::try_from_secs_f64(dt).map_err(|e| invalid(&e))?;
+                    replay.frame_times.push_back((frame, dt));
+                }
+                Entry::Input { event, .. } if !event.is_recorded() => return Err(invalid(&"key is never recorded")),
+                Entry::Input { frame, event, .. } => replay.events.push_back((frame, event)),
+            }
+        }
+        Ok(replay)
+    }
+
+    /// Removes and returns the events recorded up to and including `frame`.
+    pub fn events_until(&mut self, frame: u32) -> Vec<InputEvent> {
+        let due = self.events.iter().take_while(|(f, _)| *f <= frame).count();
+        self.events.drain(..due).map(|(_, event)| event).collect()
+    }
+
+    /// Removes and returns the time recorded for `frame`, including any
+    /// earlier frames not yet taken, or `None` if none was recorded.
+    pub fn frame_time(&mut self, frame: u32) -> Option<Duration> {
+        let due = self.frame_times.iter().take_while(|(f, _)| *f <= frame).count();
+        (due > 0).then(|| self.frame_times.drain(..due).map(|(_, dt)| dt).sum())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty() && self.frame_times.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip() {
+        let events = [
+            InputEvent::MouseButton { pressed: true },
+            InputEvent::CursorMoved(Vec2::new(12.5, 300.0)),
+            InputEvent::MouseMotion(Vec2::new(-3.0, 0.25)),
+            InputEvent::MouseButton { pressed: false },
+            InputEvent::Scroll(-1.5),
+            InputEvent::Key(KeyCode::ArrowRight),
+        ];
+        let log: String = events
+            .iter()
+            .enumerate()
+            .map(|(i, &event)| serde_json::to_string(&Entry::Input { frame: i as u32, seconds: 0.0, event }).unwrap() + "\n")
+            .collect();
+        let mut replay = EventReplay::parse(&log).unwrap();
+        assert_eq!(replay.events_until(2), events[..3]);
+        assert_eq!(replay.events_until(10), events[3..]);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn frame_times_are_replayed_per_frame() {
+        let log = [(0, 0.0), (1, 0.016), (1, 0.004), (3, 0.5)]
+            .map(|(frame, dt)| serde_json::to_string(&Entry::Frame { frame, dt }).unwrap())
+            .join("\n");
+        let mut replay = EventReplay::parse(&log).unwrap();
+        assert_eq!(replay.frame_time(0), Some(Duration::ZERO));
+        // A frame that was retried advanced time twice
+        assert_eq!(replay.frame_time(1), Some(Duration::from_secs_f64(0.016) + Duration::from_secs_f64(0.004)));
+        assert_eq!(replay.frame_time(2), None);
+        assert!(!replay.is_finished());
+        assert_eq!(replay.frame_time(3), Some(Duration::from_secs_f64(0.5)));
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn unhandled_keys_are_not_recorded() {
+        assert!(!InputEvent::Key(KeyCode::KeyA).is_recorded());
+        assert!(EventReplay::parse(r#"{"kind":"input","frame":0,"seconds":0.0,"event":{"key":"Tab"}}"#).is_ok());
+        assert!(EventReplay::parse(r#"{"kind":"input","frame":0,"seconds":0.0,"event":{"key":"KeyA"}}"#).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Sub-pixel projection jitter, groundwork for temporal anti-aliasing.
+
+use glam::{Mat4, Vec2, Vec3};
+
+/// Number of frames before the jitter pattern repeats.
+const SEQUENCE_LENGTH: u32 = 8;
+
+/// Element `index` of the Halton low-discrepancy sequence in the given base, in [0, 1).
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Jitter for the given frame in NDC units, within half a pixel in each direction.
+pub fn offset(frame: u32, width: u32, height: u32) -> Vec2 {
+    // Index 0 of the sequence is always 0, so start at 1
+    let index = frame % SEQUENCE_LENGTH + 1;
+    let pixel = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+    // NDC spans 2 units across the viewport
+    pixel * 2.0 / Vec2::new(width.max(1) as f32, height.max(1) as f32)
+}
+
+/// Shifts a projection matrix by an NDC offset.
+pub fn apply(projection: Mat4, offset: Vec2) -> Mat4 {
+    Mat4::from_translation(Vec3::new(offset.x, offset.y, 0.0)) * projection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halton_base_two_and_three() {
+        let base2: Vec<f32> = (1..=4).map(|i| halton(i, 2)).collect();
+        assert_eq!(base2, [0.5, 0.25, 0.75, 0.125]);
+
+        let base3: Vec<f32> = (1..=4).map(|i| halton(i, 3)).collect();
+        let expected = [1.0 / 3.0, 2.0 / 3.0, 1.0 / 9.0, 4.0 / 9.0];
+        for (value, expected) in base3.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn offset_stays_within_half_a_pixel() {
+        for frame in 0..SEQUENCE_LENGTH {
+            let offset = offset(frame, 800, 600);
+            assert!(offset.x.abs() <= 1.0 / 800.0);
+            assert!(offset.y.abs() <= 1.0 / 600.0);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! blink's renderer as a library: `Renderer` owns the device, pipelines and
+//! scene, and draws into the window it was set up with or into any texture
+//! view given to `Renderer::render_into`. The `blink` binary wraps it in a
+//! winit window.
+
+mod bench;
+mod clock;
+mod color_space;
+pub mod config;
+mod environment;
+mod frustum;
+mod hud;
+mod inertia;
+pub mod input;
+mod jitter;
+mod lights;
+mod mesh;
+mod occlusion;
+mod overdraw;
+mod panorama;
+mod post;
+mod renderer;
+mod scene;
+mod shaders;
+mod showcase;
+mod texture;
+mod trackball;
+mod uniform_ring;
+mod vertex;
+
+pub use renderer::{Camera, Renderer};
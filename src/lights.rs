@@ -0,0 +1,70 @@
+//! Point lights, uploaded as a fixed-size uniform array.
+
+use glam::Vec3;
+
+/// Must match the array length of `Lights.lights` in `shader.wgsl`.
+pub const MAX_LIGHTS: usize = 8;
+
+/// Colors handed out to newly added lights, in turn.
+const PALETTE: [[f32; 3]; 4] = [[1.0, 0.95, 0.8], [1.0, 0.4, 0.3], [0.3, 0.6, 1.0], [0.4, 1.0, 0.5]];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    /// Distance at which the light has fallen off to half its intensity
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl PointLight {
+    /// A light at `position` with the `index`-th palette color.
+    pub fn new(position: Vec3, index: usize) -> Self {
+        Self {
+            position: position.into(),
+            range: 4.0,
+            color: PALETTE[index % PALETTE.len()],
+            intensity: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniforms {
+    lights: [PointLight; MAX_LIGHTS],
+    count: u32,
+    _padding: [u32; 3],
+}
+
+impl LightUniforms {
+    pub fn new(lights: &[PointLight]) -> Self {
+        assert!(lights.len() <= MAX_LIGHTS, "at most {MAX_LIGHTS} point lights are supported");
+        let mut uniforms = Self { lights: [PointLight::default(); MAX_LIGHTS], count: lights.len() as u32, _padding: [0; 3] };
+        uniforms.lights[..lights.len()].copy_from_slice(lights);
+        uniforms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_matches_wgsl() {
+        // vec3 + f32 pairs pack into 16 bytes, so each light has a 32 byte array stride
+        assert_eq!(std::mem::size_of::<PointLight>(), 32);
+        // The count follows the array and the struct is padded to 16 bytes
+        assert_eq!(std::mem::size_of::<LightUniforms>(), 32 * MAX_LIGHTS + 16);
+        assert_eq!(std::mem::offset_of!(LightUniforms, count), 32 * MAX_LIGHTS);
+    }
+
+    #[test]
+    fn unused_slots_are_zeroed() {
+        let uniforms = LightUniforms::new(&[PointLight::new(Vec3::ONE, 0)]);
+        assert_eq!(uniforms.count, 1);
+        assert_eq!(uniforms.lights[0].position, [1.0; 3]);
+        assert_eq!(uniforms.lights[1].intensity, 0.0);
+    }
+}
@@ -1,423 +1,398 @@
 use winit::application::ApplicationHandler;
-use winit::event::{WindowEvent, DeviceEvent};
+use winit::event::{WindowEvent, DeviceEvent, ElementState, KeyEvent};
+use winit::keyboard::PhysicalKey;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::window::{Window, WindowId};
-use wgpu::{Instance, Device, Queue, SurfaceConfiguration, util::DeviceExt};
-use glam::{Vec3, Mat4, Quat};
-
-#[derive(Default)]
+use winit::dpi::PhysicalPosition;
+use winit::window::{CursorGrabMode, Window, WindowId};
+use glam::Vec2;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use blink::config::{Config, FrameCap, StartScene};
+use blink::input::{EventRecorder, EventReplay, InputEvent};
+use blink::Renderer;
+
+mod pacer;
+mod present;
+mod safe_mode;
+mod trace;
+
+use pacer::FramePacer;
+use present::{PresentRetry, Recovery};
+use safe_mode::RunMarker;
+use trace::FrameTrace;
+
+/// The window around a `Renderer`: owns the event loop side, i.e. frame
+/// pacing, presentation retries, cursor grabs and the input and trace logs.
 struct App {
-    window: Option<Window>,
-    instance: Option<Instance>,
-    device: Option<Device>,
-    queue: Option<Queue>,
-    config: Option<SurfaceConfiguration>,
-    camera: Camera,
-    mouse_pressed: bool,
-    render_pipeline: Option<wgpu::RenderPipeline>,
-    vertex_buffer: Option<wgpu::Buffer>,
-    index_buffer: Option<wgpu::Buffer>,
-    uniform_buffer: Option<wgpu::Buffer>,
-    uniform_bind_group: Option<wgpu::BindGroup>,
+    /// Shared with the surface presenting to it
+    window: Option<Arc<Window>>,
+    renderer: Renderer,
+    /// How the cursor is held in place while dragging to look around
+    cursor_hold: Option<CursorHold>,
+    /// Set once locking the cursor has failed, so it isn't tried on every drag
+    cursor_lock_unsupported: bool,
+    last_frame: Option<Instant>,
+    /// Start of the current frame: real time, except that replays advance it
+    /// by the recorded frame times
+    frame_clock: Option<Instant>,
+    /// Set when `--max-fps` caps the frame rate
+    pacer: Option<FramePacer>,
+    /// Backoff state while swapchain frames can't be acquired
+    present_retry: PresentRetry,
+    recorder: Option<EventRecorder>,
+    /// `--trace-csv` output
+    trace: Option<FrameTrace>,
+    /// While set, live input is ignored and recorded events are applied instead
+    replay: Option<EventReplay>,
+    /// Why graphics couldn't be set up, which ends the event loop
+    startup_error: Option<String>,
 }
 
-#[derive(Debug)]
-struct Camera {
-    position: Vec3,
-    rotation: Quat,
-    fov: f32,
-    aspect: f32,
-    near: f32,
-    far: f32,
+/// Keeps the cursor from hitting the window edge during mouse look.
+#[derive(Clone, Copy, Debug)]
+enum CursorHold {
+    /// `CursorGrabMode::Locked`; motion comes from raw device events
+    Locked,
+    /// Fallback where locking is unsupported: the cursor is warped back to
+    /// this window position after every move, and motion is its offset from it
+    Warped(Vec2),
 }
 
-impl Default for Camera {
-    fn default() -> Self {
-        Self {
-            position: Vec3::new(0.0, 0.0, 5.0),
-            rotation: Quat::IDENTITY,
-            fov: 45.0_f32.to_radians(),
-            aspect: 1.0,
-            near: 0.1,
-            far: 100.0,
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let settings = self.renderer.settings();
+        // The compositor only blends a window with what's behind it if it's created transparent
+        let transparent = settings.alpha_mode.is_some_and(|mode| mode != wgpu::CompositeAlphaMode::Opaque);
+        let window = event_loop.create_window(Window::default_attributes().with_transparent(transparent)).unwrap();
+        let refresh_rate = pacer::monitor_refresh_rate(&window);
+        match refresh_rate {
+            Some(hz) => println!("Monitor refresh rate: {hz:.2} Hz"),
+            None => println!("Monitor refresh rate unknown; assuming {} Hz", pacer::FALLBACK_REFRESH_HZ),
         }
-    }
-}
+        self.pacer = settings.max_fps.map(|cap| {
+            let fps = match cap {
+                FrameCap::Fps(fps) => fps,
+                FrameCap::Monitor => refresh_rate.unwrap_or(pacer::FALLBACK_REFRESH_HZ),
+            };
+            println!("Capping the frame rate at {fps:.2} fps");
+            FramePacer::new(fps, Instant::now())
+        });
+        let window = Arc::new(window);
+        self.window = Some(window.clone());
 
-impl Camera {
-    fn view_matrix(&self) -> Mat4 {
-        Mat4::from_translation(-self.position) * Mat4::from_quat(self.rotation)
-    }
+        // Initialize graphics
+        let size = window.inner_size();
+        if let Err(e) = start_graphics(&mut self.renderer, Some((window.clone().into(), (size.width, size.height)))) {
+            self.startup_error = Some(e);
+            event_loop.exit();
+            return;
+        }
 
-    fn projection_matrix(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+        // Request initial redraw
+        window.request_redraw();
     }
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-    color: [f32; 3],
-}
 
-impl Vertex {
-    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let due = [self.pacer.as_ref().map(FramePacer::next_frame), self.present_retry.retry_at()];
+        let Some(due) = due.into_iter().flatten().max() else { return };
+        if Instant::now() >= due {
+            self.window.as_ref().unwrap().request_redraw();
+        } else {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(due));
         }
     }
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Uniforms {
-    view_proj: [[f32; 4]; 4],
-}
-
-
-
-impl ApplicationHandler for App {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop.create_window(Window::default_attributes()).unwrap();
-        self.window = Some(window);
-        self.mouse_pressed = false;
-        
-        // Initialize graphics
-        self.init_graphics();
-        
-        // Request initial redraw
-        self.window.as_ref().unwrap().request_redraw();
-    }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
+                if let Some(trace) = self.trace.take() {
+                    trace.finish();
+                }
                 event_loop.exit();
             },
             WindowEvent::RedrawRequested => {
+                self.replay_events();
+                self.begin_frame();
                 self.render();
-                // Request continuous redraws
-                self.window.as_ref().unwrap().request_redraw();
+                match &mut self.pacer {
+                    // about_to_wait requests the next frame once it is due
+                    Some(pacer) => pacer.frame_presented(Instant::now()),
+                    // Backing off after a failed frame; about_to_wait requests the retry
+                    None if self.present_retry.retry_at().is_some() => (),
+                    // Request continuous redraws
+                    None => self.window.as_ref().unwrap().request_redraw(),
+                }
             }
             WindowEvent::Resized(physical_size) => {
-                if let (Some(device), Some(instance), Some(config)) = 
-                    (&self.device, &self.instance, &mut self.config) {
-                    config.width = physical_size.width;
-                    config.height = physical_size.height;
-                    let window = self.window.as_ref().unwrap();
-                    let surface = instance.create_surface(window).unwrap();
-                    surface.configure(device, config);
-                    self.camera.aspect = physical_size.width as f32 / physical_size.height as f32;
-                }
+                self.renderer.resize(physical_size.width, physical_size.height);
                 // Request redraw after resize
                 self.window.as_ref().unwrap().request_redraw();
             }
-            WindowEvent::MouseInput { state, button, .. } => {
-                if button == winit::event::MouseButton::Left {
-                    self.mouse_pressed = state == winit::event::ElementState::Pressed;
+            WindowEvent::MouseInput { state, button: winit::event::MouseButton::Left, .. } => {
+                let pressed = state == ElementState::Pressed;
+                if pressed && self.replay.is_none() {
+                    self.hold_cursor();
+                } else if !pressed {
+                    self.release_cursor();
                 }
+                self.input(InputEvent::MouseButton { pressed });
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                // Handle zoom with mouse wheel
-                let zoom_speed = 0.5;
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        // Move camera forward/backward based on scroll
-                        let zoom_delta = y * zoom_speed;
-                        self.camera.position.z -= zoom_delta;
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(pos) => {
-                        // Alternative pixel-based scrolling
-                        let zoom_delta = pos.y as f32 * zoom_speed * 0.01;
-                        self.camera.position.z -= zoom_delta;
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                match self.cursor_hold {
+                    Some(CursorHold::Warped(center)) => {
+                        // Skips the move caused by warping back
+                        if position != center {
+                            self.input(InputEvent::MouseMotion(position - center));
+                            let _ = self.window.as_ref().unwrap().set_cursor_position(PhysicalPosition::new(center.x, center.y));
+                        }
                     }
+                    _ => self.input(InputEvent::CursorMoved(position)),
                 }
-                // Request redraw after zoom
-                self.window.as_ref().unwrap().request_redraw();
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(key), state: ElementState::Pressed, repeat: false, .. },
+                ..
+            } => self.input(InputEvent::Key(key)),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                    // Treat 100 pixels as one line
+                    winit::event::MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.01,
+                };
+                self.input(InputEvent::Scroll(lines));
             }
             _ => (),
         }
     }
 
     fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: winit::event::DeviceId, event: DeviceEvent) {
-        match event {
-            DeviceEvent::MouseMotion { delta } => {
-                // Only rotate camera when left mouse button is held
-                if let Some(_window) = &self.window {
-                    if self.mouse_pressed {
-                        let sensitivity = 0.01;
-                        let delta_x = delta.0 as f32 * sensitivity;
-                        let delta_y = delta.1 as f32 * sensitivity;
-                        
-                        let rot_y = Quat::from_axis_angle(Vec3::Y, delta_x);
-                        let rot_x = Quat::from_axis_angle(Vec3::X, delta_y);
-                        self.camera.rotation = rot_y * rot_x * self.camera.rotation;
-                    }
-                }
-            }
-            _ => (),
+        // With a warped cursor, motion is taken from cursor moves instead
+        if let DeviceEvent::MouseMotion { delta } = event
+            && !matches!(self.cursor_hold, Some(CursorHold::Warped(_)))
+        {
+            self.input(InputEvent::MouseMotion(Vec2::new(delta.0 as f32, delta.1 as f32)));
         }
     }
 }
 
 impl App {
-    fn init_graphics(&mut self) {
-        let window = self.window.as_ref().unwrap();
-        
-        // Create instance
-        let instance = Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-            flags: wgpu::InstanceFlags::default(),
-            gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
-        });
-
-        // Create surface
-        let surface =   instance.create_surface(window).unwrap();
-
-        // Get adapter
-        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        })).unwrap();
-
-        // Create device and queue
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-            },
-            None,
-        )).unwrap();
-
-        self.device = Some(device);
-        self.queue = Some(queue);
-        self.instance = Some(instance);
-
-        // Configure surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats[0];
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: window.inner_size().width,
-            height: window.inner_size().height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        surface.configure(&self.device.as_ref().unwrap(), &config);
-        self.config = Some(config);
-
-        // Create shaders
-        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-
-        // Create render pipeline
-        let render_pipeline_layout = self.device.as_ref().unwrap().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&self.create_bind_group_layout()],
-            push_constant_ranges: &[],
-        });
-
-        let render_pipeline = self.device.as_ref().unwrap().create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+    fn new(renderer: Renderer) -> Self {
+        Self {
+            window: None,
+            renderer,
+            cursor_hold: None,
+            cursor_lock_unsupported: false,
+            last_frame: None,
+            frame_clock: None,
+            pacer: None,
+            present_retry: PresentRetry::default(),
+            recorder: None,
+            trace: None,
+            replay: None,
+            startup_error: None,
+        }
+    }
 
-        self.render_pipeline = Some(render_pipeline);
+    /// Opens the `--record` / `--replay` input logs and the `--trace-csv` file.
+    fn open_logs(&mut self) {
+        let settings = self.renderer.settings();
+        if let Some(path) = &settings.trace_csv {
+            match FrameTrace::create(path, Instant::now()) {
+                Ok(trace) => {
+                    println!("Tracing frame times to {}", path.display());
+                    self.trace = Some(trace);
+                }
+                Err(e) => eprintln!("Failed to create frame trace {}: {e}", path.display()),
+            }
+        }
+        if let Some(path) = &settings.replay {
+            match EventReplay::load(path) {
+                Ok(replay) => {
+                    println!("Replaying input from {}; live input is ignored until it finishes", path.display());
+                    self.replay = Some(replay);
+                }
+                Err(e) => eprintln!("Failed to load input log {e}"),
+            }
+        }
+        if let Some(path) = &settings.record {
+            match EventRecorder::create(path) {
+                Ok(recorder) => {
+                    println!("Recording input to {}", path.display());
+                    self.recorder = Some(recorder);
+                }
+                Err(e) => eprintln!("Failed to create input log {}: {e}", path.display()),
+            }
+        }
+    }
 
-        // Create vertex buffer (simple cube)
-        let vertices = create_cube_vertices();
-        let vertex_buffer = self.device.as_ref().unwrap().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        self.vertex_buffer = Some(vertex_buffer);
+    /// Locks the cursor for mouse look, or if the platform can't, confines it
+    /// and warps it back to the window center after every move.
+    fn hold_cursor(&mut self) {
+        let window = self.window.as_ref().unwrap();
+        if !self.cursor_lock_unsupported {
+            match window.set_cursor_grab(CursorGrabMode::Locked) {
+                Ok(()) => {
+                    window.set_cursor_visible(false);
+                    self.cursor_hold = Some(CursorHold::Locked);
+                    return;
+                }
+                Err(e) => {
+                    println!("Cursor locking is unsupported ({e}); warping the cursor to the window center during mouse look");
+                    self.cursor_lock_unsupported = true;
+                }
+            }
+        }
+        let size = window.inner_size();
+        // Whole pixels, so the warp lands exactly on the center and causes no motion
+        let center = Vec2::new((size.width / 2) as f32, (size.height / 2) as f32);
+        if let Err(e) = window.set_cursor_position(PhysicalPosition::new(center.x, center.y)) {
+            // Motion still comes from raw device events; the cursor just isn't held
+            println!("Cannot warp the cursor either ({e})");
+            return;
+        }
+        let _ = window.set_cursor_grab(CursorGrabMode::Confined);
+        window.set_cursor_visible(false);
+        self.cursor_hold = Some(CursorHold::Warped(center));
+    }
 
-        // Create index buffer
-        let indices = create_cube_indices();
-        let index_buffer = self.device.as_ref().unwrap().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        self.index_buffer = Some(index_buffer);
+    fn release_cursor(&mut self) {
+        let Some(hold) = self.cursor_hold.take() else { return };
+        let window = self.window.as_ref().unwrap();
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        // Put a warped cursor back where the drag started
+        if let (CursorHold::Warped(_), Some(position)) = (hold, self.renderer.cursor_position()) {
+            let _ = window.set_cursor_position(PhysicalPosition::new(position.x, position.y));
+        }
+    }
 
-        // Create uniform buffer and bind group
-        let uniform_buffer = self.device.as_ref().unwrap().create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[Uniforms {
-                view_proj: (self.camera.projection_matrix() * self.camera.view_matrix()).to_cols_array_2d(),
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        self.uniform_buffer = Some(uniform_buffer);
+    /// Handles live input: records it, or drops it while a replay is running.
+    fn input(&mut self, event: InputEvent) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(self.renderer.frame_index(), &event);
+        }
+        self.renderer.apply_input(event);
+    }
 
-        let bind_group = self.device.as_ref().unwrap().create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.create_bind_group_layout(),
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: self.uniform_buffer.as_ref().unwrap().as_entire_binding(),
-            }],
-            label: Some("uniform_bind_group"),
-        });
-        self.uniform_bind_group = Some(bind_group);
+    /// Feeds the replayed events recorded up to the frame about to be drawn.
+    fn replay_events(&mut self) {
+        let Some(replay) = &mut self.replay else { return };
+        let events = replay.events_until(self.renderer.frame_index());
+        let finished = replay.is_finished();
+        for event in events {
+            self.renderer.apply_input(event);
+        }
+        if finished {
+            println!("Replay finished");
+            self.replay = None;
+        }
     }
 
-    fn create_bind_group_layout(&self) -> wgpu::BindGroupLayout {
-        self.device.as_ref().unwrap().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: Some("uniform_bind_group_layout"),
-        })
+    /// Measures the time since the previous frame, or during a replay takes
+    /// the recorded one, and advances the frame clock and the scene by it.
+    fn begin_frame(&mut self) {
+        let now = Instant::now();
+        let mut frame_time = Duration::ZERO;
+        if let Some(last) = self.last_frame.replace(now) {
+            frame_time = now - last;
+            if let Some(trace) = &mut self.trace {
+                trace.record(now, frame_time);
+            }
+        }
+        let frame = self.renderer.frame_index();
+        if let Some(dt) = self.replay.as_mut().and_then(|replay| replay.frame_time(frame)) {
+            frame_time = dt;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_frame(frame, frame_time);
+        }
+        let clock = self.frame_clock.map_or(now, |clock| clock + frame_time);
+        self.frame_clock = Some(clock);
+        self.renderer.advance(clock, frame_time);
     }
 
     fn render(&mut self) {
-        if let (Some(device), Some(instance), Some(queue), Some(config), Some(pipeline), Some(vertex_buffer), Some(index_buffer), Some(uniform_bind_group)) = 
-            (&self.device, &self.instance, &self.queue, &self.config, &self.render_pipeline, &self.vertex_buffer, &self.index_buffer, &self.uniform_bind_group) {
-            
-            let window = self.window.as_ref().unwrap();
-            let surface =  instance.create_surface(window) .unwrap();
-            surface.configure(device, config);
-            let frame = surface.get_current_texture().unwrap();
-            let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-            
-            // Update uniforms
-            let uniforms = Uniforms {
-                view_proj: (self.camera.projection_matrix() * self.camera.view_matrix()).to_cols_array_2d(),
-            };
-            queue.write_buffer(&self.uniform_buffer.as_ref().unwrap(), 0, bytemuck::cast_slice(&[uniforms]));
-
-            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-            {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                });
-
-                render_pass.set_pipeline(pipeline);
-                render_pass.set_bind_group(0, uniform_bind_group, &[]);
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..36, 0, 0..1);
+        match self.renderer.draw_frame() {
+            Ok(()) => {
+                if let Some(skipped) = self.present_retry.succeeded() {
+                    println!("Presenting again after skipping {skipped} frames");
+                }
             }
-
-            queue.submit(std::iter::once(encoder.finish()));
-            frame.present();
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                eprintln!("Out of memory while acquiring a frame; cannot continue");
+                std::process::exit(1);
+            }
+            Err(error) => match self.present_retry.failed(Instant::now()) {
+                Recovery::Retry(delay) => eprintln!("Failed to acquire a frame ({error}); retrying in {delay:?}"),
+                Recovery::Reconfigure(delay) => {
+                    eprintln!("Failed to acquire a frame ({error}) repeatedly; reconfiguring the surface and retrying in {delay:?}");
+                    let size = self.window.as_ref().unwrap().inner_size();
+                    self.renderer.resize(size.width, size.height);
+                }
+            },
         }
     }
 }
 
-fn create_cube_vertices() -> Vec<Vertex> {
-    vec![
-        // Front face
-        Vertex { position: [-1.0, -1.0,  1.0], color: [1.0, 0.0, 0.0] },
-        Vertex { position: [ 1.0, -1.0,  1.0], color: [0.0, 1.0, 0.0] },
-        Vertex { position: [ 1.0,  1.0,  1.0], color: [0.0, 0.0, 1.0] },
-        Vertex { position: [-1.0,  1.0,  1.0], color: [1.0, 1.0, 0.0] },
-        // Back face
-        Vertex { position: [-1.0, -1.0, -1.0], color: [1.0, 0.0, 1.0] },
-        Vertex { position: [-1.0,  1.0, -1.0], color: [0.0, 1.0, 1.0] },
-        Vertex { position: [ 1.0,  1.0, -1.0], color: [1.0, 1.0, 1.0] },
-        Vertex { position: [ 1.0, -1.0, -1.0], color: [0.5, 0.5, 0.5] },
-    ]
+/// Sets up graphics and the scene.
+fn start_graphics(renderer: &mut Renderer, target: Option<(wgpu::SurfaceTarget<'static>, (u32, u32))>) -> Result<(), String> {
+    renderer.init_graphics(target)?;
+    renderer.arrange_objects();
+    Ok(())
 }
 
-fn create_cube_indices() -> Vec<u16> {
-    vec![
-        0, 1, 2,  2, 3, 0,  // front
-        4, 5, 6,  6, 7, 4,  // back
-        0, 4, 7,  7, 1, 0,  // bottom
-        2, 6, 5,  5, 3, 2,  // top
-        0, 3, 5,  5, 4, 0,  // left
-        1, 7, 6,  6, 2, 1,  // right
-    ]
+fn main() -> ExitCode {
+    let mut settings = Config::from_args(std::env::args().skip(1));
+    let (marker, crashed) = RunMarker::create(&RunMarker::default_dir());
+    if crashed && !settings.safe_mode {
+        println!("The previous run did not exit cleanly; starting in safe mode (GL backend, software adapter, no MSAA, cube only)");
+        settings.apply_safe_mode();
+    }
+    // A failed setup is reported rather than a crash, so it doesn't leave the marker behind
+    let result = run(settings);
+    marker.remove();
+    result.unwrap_or_else(|e| {
+        eprintln!("{e}; cannot start");
+        ExitCode::FAILURE
+    })
 }
 
-fn main() {
+/// Runs the self-test, benchmark, panorama export or window `settings` ask for.
+fn run(mut settings: Config) -> Result<ExitCode, String> {
+    if settings.selftest {
+        // A known scene: the cube covers the center, and no overlay does
+        settings.models.clear();
+        settings.start = StartScene::Cube;
+        settings.crosshair = false;
+        settings.hud = false;
+        let mut renderer = Renderer::new(settings);
+        start_graphics(&mut renderer, None)?;
+        return Ok(if renderer.run_selftest() { ExitCode::SUCCESS } else { ExitCode::FAILURE });
+    }
+    if let Some(frames) = settings.bench_frames {
+        let mut renderer = Renderer::new(settings);
+        start_graphics(&mut renderer, None)?;
+        renderer.run_benchmark(frames);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(path) = settings.panorama.clone() {
+        let mut renderer = Renderer::new(settings);
+        start_graphics(&mut renderer, None)?;
+        renderer.save_panorama(&path);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut app = App::new(Renderer::new(settings));
+    app.open_logs();
+
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
-
-    let mut app = App::default();
     let _ = event_loop.run_app(&mut app);
-}
\ No newline at end of file
+    app.startup_error.map_or(Ok(ExitCode::SUCCESS), Err)
+}
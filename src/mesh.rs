@@ -0,0 +1,669 @@
+//! CPU-side meshes: the built-in cube and minimal Wavefront OBJ and STL loaders.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use glam::{Mat4, Vec3};
+
+use crate::vertex::Vertex;
+
+/// Color given to vertices of meshes that don't specify one.
+const DEFAULT_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+/// Triangles whose corner angle has a sine below this are treated as
+/// degenerate and left out of normals; relative, so it works at any scale.
+const DEGENERATE_SINE: f32 = 1e-6;
+/// Normal of vertices that only belong to degenerate triangles
+const FALLBACK_NORMAL: Vec3 = Vec3::Y;
+
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Radius of the sphere around `center()` that encloses the box.
+    pub fn radius(&self) -> f32 {
+        self.size().length() * 0.5
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    /// Bounds of this box after transforming all eight corners.
+    pub fn transformed(&self, matrix: Mat4) -> Aabb {
+        let corners = (0..8).map(|i| {
+            let pick = |bit, lo: f32, hi: f32| if i & bit == 0 { lo } else { hi };
+            matrix.transform_point3(Vec3::new(
+                pick(1, self.min.x, self.max.x),
+                pick(2, self.min.y, self.max.y),
+                pick(4, self.min.z, self.max.z),
+            ))
+        });
+        Aabb::from_points(corners).unwrap()
+    }
+
+    /// Distance along the ray to where it enters the box, or the start of
+    /// `range` if it is inside by then; `None` if it misses within `range`.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3, range: RangeInclusive<f32>) -> Option<f32> {
+        // Slab test; division by zero gives infinities, which compare correctly
+        let t1 = (self.min - origin) / direction;
+        let t2 = (self.max - origin) / direction;
+        let near = t1.min(t2).max_element().max(*range.start());
+        let far = t1.max(t2).min_element().min(*range.end());
+        (near <= far).then_some(near)
+    }
+
+    fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Aabb> {
+        points.into_iter().fold(None, |bounds, p| {
+            Some(match bounds {
+                None => Aabb { min: p, max: p },
+                Some(b) => Aabb { min: b.min.min(p), max: b.max.max(p) },
+            })
+        })
+    }
+}
+
+/// A sphere enclosing a mesh, cheaper to test against the frustum than a box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// A sphere enclosing this one after `matrix`; non-uniform scales grow
+    /// the radius by the largest of them.
+    pub fn transformed(&self, matrix: Mat4) -> BoundingSphere {
+        let scale = [matrix.x_axis, matrix.y_axis, matrix.z_axis].map(|axis| axis.truncate().length());
+        BoundingSphere { center: matrix.transform_point3(self.center), radius: self.radius * scale.into_iter().fold(0.0, f32::max) }
+    }
+}
+
+impl Mesh {
+    pub fn cube() -> Self {
+        Self { vertices: create_cube_vertices(), indices: create_cube_indices() }
+    }
+
+    /// A single triangle facing +Z with red, green and blue corners.
+    pub fn triangle() -> Self {
+        let vertices = vec![
+            Vertex { position: [-1.0, -1.0, 0.0], color: [1.0, 0.0, 0.0], uv: [0.0, 1.0], normal: [0.0, 0.0, 1.0] },
+            Vertex { position: [ 1.0, -1.0, 0.0], color: [0.0, 1.0, 0.0], uv: [1.0, 1.0], normal: [0.0, 0.0, 1.0] },
+            Vertex { position: [ 0.0,  1.0, 0.0], color: [0.0, 0.0, 1.0], uv: [0.5, 0.0], normal: [0.0, 0.0, 1.0] },
+        ];
+        Self { vertices, indices: vec![0, 1, 2] }
+    }
+
+    /// Line segments in a single color, for drawing with a line-list pipeline.
+    pub fn lines(segments: impl IntoIterator<Item = (Vec3, Vec3)>, color: [f32; 3]) -> Self {
+        let vertices: Vec<Vertex> = segments
+            .into_iter()
+            .flat_map(|(start, end)| [start, end])
+            .map(|position| Vertex { position: position.into(), color, uv: [0.0; 2], normal: [0.0; 3] })
+            .collect();
+        let indices = (0..vertices.len() as u32).collect();
+        Self { vertices, indices }
+    }
+
+    /// Unit UV sphere with `segments` slices around the equator and half as
+    /// many stacks from pole to pole; colored by its normals.
+    pub fn sphere(segments: u32) -> Self {
+        let segments = segments.max(3);
+        let stacks = (segments / 2).max(2);
+        let mut vertices = Vec::new();
+        for stack in 0..=stacks {
+            let v = stack as f32 / stacks as f32;
+            let (sin_theta, cos_theta) = (v * std::f32::consts::PI).sin_cos();
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+                let normal = Vec3::new(sin_theta * sin_phi, cos_theta, sin_theta * cos_phi);
+                vertices.push(Vertex {
+                    position: normal.into(),
+                    color: (normal * 0.5 + 0.5).into(),
+                    uv: [u, v],
+                    normal: normal.into(),
+                });
+            }
+        }
+        let row = segments + 1;
+        let indices = (0..stacks)
+            .flat_map(|stack| (0..segments).map(move |segment| stack * row + segment))
+            .flat_map(|top_left| {
+                let bottom_left = top_left + row;
+                [top_left, bottom_left, bottom_left + 1, top_left, bottom_left + 1, top_left + 1]
+            })
+            .collect();
+        Self { vertices, indices }
+    }
+
+    /// Bounds of all vertex positions, `None` for an empty mesh.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| Vec3::from(v.position)))
+    }
+
+    /// Sphere around the center of `bounds()` reaching the furthest vertex,
+    /// `None` for an empty mesh. Not the smallest enclosing sphere, but close
+    /// for most meshes and tighter than the box's circumscribed sphere.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        let center = self.bounds()?.center();
+        let radius = self.vertices.iter().map(|v| center.distance(Vec3::from(v.position))).fold(0.0, f32::max);
+        Some(BoundingSphere { center, radius })
+    }
+
+    /// Smooth vertex normals: the area-weighted average of the normals of
+    /// the triangles sharing each vertex. Degenerate (zero-area) triangles
+    /// are skipped; vertices left without a normal get `FALLBACK_NORMAL`.
+    pub fn compute_normals(&mut self) {
+        let mut sums = vec![Vec3::ZERO; self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(self.vertices[triangle[i] as usize].position));
+            let Some(normal) = face_normal(a, b, c) else { continue };
+            for &index in triangle {
+                sums[index as usize] += normal;
+            }
+        }
+        for (vertex, sum) in self.vertices.iter_mut().zip(sums) {
+            vertex.normal = sum.try_normalize().unwrap_or(FALLBACK_NORMAL).into();
+        }
+    }
+
+    /// Flips triangles whose winding disagrees with their vertex normals, so
+    /// back-face culling keeps the side the normals point to, then
+    /// recomputes the normals from the fixed winding. A triangle's reference
+    /// is the sum of its corners' normals; where those were averaged from the
+    /// winding, as for OBJ files, neighbors outvote the odd triangle out.
+    /// Returns the number of triangles flipped.
+    pub fn fix_winding(&mut self) -> usize {
+        let mut flipped = 0;
+        for triangle in self.indices.chunks_exact_mut(3) {
+            let corners = [0, 1, 2].map(|i| &self.vertices[triangle[i] as usize]);
+            let [a, b, c] = corners.map(|v| Vec3::from(v.position));
+            let Some(geometric) = face_normal(a, b, c) else { continue };
+            let reference: Vec3 = corners.iter().map(|v| Vec3::from(v.normal)).sum();
+            if geometric.dot(reference) < 0.0 {
+                triangle.swap(1, 2);
+                flipped += 1;
+            }
+        }
+        if flipped > 0 {
+            self.compute_normals();
+        }
+        flipped
+    }
+
+    /// Corner positions of every triangle, for ray casts on the CPU.
+    pub fn triangles(&self) -> Vec<[Vec3; 3]> {
+        self.indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|i| Vec3::from(self.vertices[triangle[i] as usize].position)))
+            .collect()
+    }
+
+    /// The embedded sample model, a vertex-colored torus knot.
+    pub fn sample() -> Result<Self, String> {
+        Self::load_obj_from_bytes(SAMPLE_MODEL)
+    }
+
+    /// Loads a model file, picking the format by extension: `.stl` for STL,
+    /// anything else is read as OBJ.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let is_stl = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("stl"));
+        if is_stl { Self::load_stl(path) } else { Self::load_obj(path) }
+    }
+
+    /// Loads an STL file; see `load_stl_from_bytes`.
+    pub fn load_stl(path: impl AsRef<Path>) -> Result<Self, String> {
+        read_model(path.as_ref(), Self::load_stl_from_bytes)
+    }
+
+    /// Parses an ASCII or binary STL model. STL has neither colors nor shared
+    /// vertices, so every triangle gets three vertices of its own with the
+    /// facet normal (flat shading) and the default color.
+    pub fn load_stl_from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        parse_stl(bytes)
+    }
+
+    /// Loads a Wavefront OBJ file; see `load_obj_from_bytes`.
+    pub fn load_obj(path: impl AsRef<Path>) -> Result<Self, String> {
+        read_model(path.as_ref(), Self::load_obj_from_bytes)
+    }
+
+    /// Parses a UTF-8 Wavefront OBJ model. Supports `v` (with optional vertex
+    /// colors), `vt` and polygonal `f` records; everything else is ignored.
+    pub fn load_obj_from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        std::str::from_utf8(bytes).map_err(|e| format!("not UTF-8 text: {e}")).and_then(parse_obj)
+    }
+}
+
+/// Reads a whole model file and parses it from memory, prefixing errors with the path.
+fn read_model(path: &Path, parse: impl FnOnce(&[u8]) -> Result<Mesh, String>) -> Result<Mesh, String> {
+    std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| parse(&bytes))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The default scene's model, embedded so it needs no files at runtime.
+const SAMPLE_MODEL: &[u8] = include_bytes!("../assets/torus_knot.obj");
+
+/// Area-weighted normal of a triangle (its length is twice the area), or
+/// `None` if the triangle is degenerate or has non-finite corners.
+fn face_normal(a: Vec3, b: Vec3, c: Vec3) -> Option<Vec3> {
+    let (edge1, edge2) = (b - a, c - a);
+    let normal = edge1.cross(edge2);
+    // |e1 x e2| = |e1| |e2| sin(angle)
+    let threshold = DEGENERATE_SINE * DEGENERATE_SINE * edge1.length_squared() * edge2.length_squared();
+    (normal.is_finite() && normal.length_squared() > threshold).then_some(normal)
+}
+
+/// Distance along the ray to the nearest triangle it hits from either side
+/// within `range`, in units of `direction`'s length; `None` if it misses
+/// them all.
+pub fn intersect_triangles(triangles: &[[Vec3; 3]], origin: Vec3, direction: Vec3, range: RangeInclusive<f32>) -> Option<f32> {
+    triangles
+        .iter()
+        .filter_map(|&[a, b, c]| {
+            // Möller–Trumbore
+            let edge1 = b - a;
+            let edge2 = c - a;
+            let p = direction.cross(edge2);
+            let determinant = edge1.dot(p);
+            if determinant.abs() < f32::EPSILON {
+                return None;
+            }
+            let offset = origin - a;
+            let u = offset.dot(p) / determinant;
+            let q = offset.cross(edge1);
+            let v = direction.dot(q) / determinant;
+            let t = edge2.dot(q) / determinant;
+            (u >= 0.0 && v >= 0.0 && u + v <= 1.0 && range.contains(&t)).then_some(t)
+        })
+        .min_by(f32::total_cmp)
+}
+
+fn parse_obj(source: &str) -> Result<Mesh, String> {
+    let mut positions: Vec<([f32; 3], [f32; 3])> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut mesh = Mesh { vertices: Vec::new(), indices: Vec::new() };
+    // OBJ indexes positions and UVs separately; each distinct pair becomes one vertex
+    let mut unique: HashMap<(usize, Option<usize>), u32> = HashMap::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", number + 1);
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("v") => {
+                let values = parse_floats(fields).ok_or_else(|| error("invalid vertex"))?;
+                let color = match values.len() {
+                    3 | 4 => DEFAULT_COLOR,
+                    6 => [values[3], values[4], values[5]],
+                    _ => return Err(error("expected 3 coordinates and an optional RGB color")),
+                };
+                let position = [values[0], values[1], values[2]];
+                positions.push((position, color));
+            }
+            Some("vt") => {
+                let values = parse_floats(fields).ok_or_else(|| error("invalid texture coordinate"))?;
+                if values.len() < 2 {
+                    return Err(error("expected at least 2 texture coordinates"));
+                }
+                // OBJ puts v = 0 at the bottom of the image, wgpu at the top
+                uvs.push([values[0], 1.0 - values[1]]);
+            }
+            Some("f") => {
+                let mut corners = Vec::new();
+                for corner in fields {
+                    let mut refs = corner.split('/');
+                    let position = resolve_index(refs.next(), positions.len()).ok_or_else(|| error("invalid face"))?;
+                    let uv = match refs.next() {
+                        None | Some("") => None,
+                        uv => Some(resolve_index(uv, uvs.len()).ok_or_else(|| error("invalid face"))?),
+                    };
+                    let index = *unique.entry((position, uv)).or_insert_with(|| {
+                        let (position, color) = positions[position];
+                        let uv = uv.map_or([0.0, 0.0], |i| uvs[i]);
+                        mesh.vertices.push(Vertex { position, color, uv, normal: [0.0; 3] });
+                        mesh.vertices.len() as u32 - 1
+                    });
+                    corners.push(index);
+                }
+                if corners.len() < 3 {
+                    return Err(error("face needs at least 3 vertices"));
+                }
+                // Triangulate polygons as a fan
+                for i in 1..corners.len() - 1 {
+                    mesh.indices.extend([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if mesh.indices.is_empty() {
+        return Err("no faces".to_string());
+    }
+    mesh.compute_normals();
+    Ok(mesh)
+}
+
+/// Size of a binary STL header and facet count, and of one facet record
+const STL_HEADER_SIZE: usize = 84;
+const STL_FACET_SIZE: usize = 50;
+
+fn parse_stl(bytes: &[u8]) -> Result<Mesh, String> {
+    // Binary files may start with "solid" too, so a size matching the
+    // binary layout decides first
+    let binary_count = bytes
+        .get(80..STL_HEADER_SIZE)
+        .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize)
+        .filter(|&count| bytes.len() == STL_HEADER_SIZE + count * STL_FACET_SIZE);
+    let facets = match binary_count {
+        Some(count) => parse_binary_stl(&bytes[STL_HEADER_SIZE..], count),
+        None if bytes.starts_with(b"solid") => {
+            let source = std::str::from_utf8(bytes).map_err(|_| "ASCII STL is not valid UTF-8".to_string())?;
+            parse_ascii_stl(source)?
+        }
+        None => return Err("truncated binary STL".to_string()),
+    };
+    if facets.is_empty() {
+        return Err("no facets".to_string());
+    }
+    let vertices = facets
+        .iter()
+        .flat_map(|&(normal, corners)| {
+            // Exporters may leave the normal zeroed; the winding gives it then
+            let [a, b, c] = corners;
+            let normal = normal
+                .try_normalize()
+                .or_else(|| face_normal(a, b, c).map(Vec3::normalize))
+                .unwrap_or(FALLBACK_NORMAL);
+            corners.map(|position| Vertex { position: position.into(), color: DEFAULT_COLOR, uv: [0.0; 2], normal: normal.into() })
+        })
+        .collect::<Vec<_>>();
+    let indices = (0..vertices.len() as u32).collect();
+    Ok(Mesh { vertices, indices })
+}
+
+/// `(normal, corners)` of each facet
+type Facet = (Vec3, [Vec3; 3]);
+
+fn parse_binary_stl(records: &[u8], count: usize) -> Vec<Facet> {
+    records
+        .chunks_exact(STL_FACET_SIZE)
+        .take(count)
+        .map(|record| {
+            let float = |i: usize| f32::from_le_bytes(record[i * 4..i * 4 + 4].try_into().unwrap());
+            let vector = |i: usize| Vec3::new(float(i), float(i + 1), float(i + 2));
+            // The trailing 2-byte attribute count is ignored
+            (vector(0), [vector(3), vector(6), vector(9)])
+        })
+        .collect()
+}
+
+fn parse_ascii_stl(source: &str) -> Result<Vec<Facet>, String> {
+    // The first line is "solid" and an optional name, which may contain anything
+    let body = source.split_once('\n').map_or("", |(_, rest)| rest);
+    let mut tokens = body.split_whitespace();
+    let mut facets = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "facet" => {
+                let facet = parse_stl_facet(&mut tokens).map_err(|e| format!("facet {}: {e}", facets.len() + 1))?;
+                facets.push(facet);
+            }
+            "endsolid" => break,
+            other => return Err(format!("unexpected \"{other}\" after facet {}", facets.len())),
+        }
+    }
+    Ok(facets)
+}
+
+/// Parses the rest of a facet after its `facet` keyword.
+fn parse_stl_facet<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Facet, String> {
+    expect_keyword(tokens, "normal")?;
+    let normal = parse_stl_vector(tokens).ok_or("invalid normal")?;
+    expect_keyword(tokens, "outer")?;
+    expect_keyword(tokens, "loop")?;
+    let mut corners = [Vec3::ZERO; 3];
+    for corner in &mut corners {
+        expect_keyword(tokens, "vertex")?;
+        *corner = parse_stl_vector(tokens).ok_or("invalid vertex")?;
+    }
+    expect_keyword(tokens, "endloop")?;
+    expect_keyword(tokens, "endfacet")?;
+    Ok((normal, corners))
+}
+
+fn expect_keyword<'a>(tokens: &mut impl Iterator<Item = &'a str>, keyword: &str) -> Result<(), String> {
+    match tokens.next() {
+        Some(token) if token == keyword => Ok(()),
+        _ => Err(format!("expected \"{keyword}\"")),
+    }
+}
+
+fn parse_stl_vector<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Vec3> {
+    let mut component = || tokens.next()?.parse::<f32>().ok();
+    Some(Vec3::new(component()?, component()?, component()?))
+}
+
+fn parse_floats<'a>(fields: impl Iterator<Item = &'a str>) -> Option<Vec<f32>> {
+    fields.map(|f| f.parse().ok()).collect()
+}
+
+/// Converts a 1-based (or negative, relative to the end) OBJ index to a 0-based one.
+fn resolve_index(field: Option<&str>, len: usize) -> Option<usize> {
+    let index: i64 = field?.parse().ok()?;
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+    (0..len as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+fn create_cube_vertices() -> Vec<Vertex> {
+    // 4 vertices per face so every face gets its own 0-1 UV square
+    vec![
+        // Front face
+        Vertex { position: [-1.0, -1.0,  1.0], color: [1.0, 0.0, 0.0], uv: [0.0, 1.0], normal: [ 0.0,  0.0,  1.0] },
+        Vertex { position: [ 1.0, -1.0,  1.0], color: [0.0, 1.0, 0.0], uv: [1.0, 1.0], normal: [ 0.0,  0.0,  1.0] },
+        Vertex { position: [ 1.0,  1.0,  1.0], color: [0.0, 0.0, 1.0], uv: [1.0, 0.0], normal: [ 0.0,  0.0,  1.0] },
+        Vertex { position: [-1.0,  1.0,  1.0], color: [1.0, 1.0, 0.0], uv: [0.0, 0.0], normal: [ 0.0,  0.0,  1.0] },
+        // Back face
+        Vertex { position: [ 1.0, -1.0, -1.0], color: [0.5, 0.5, 0.5], uv: [0.0, 1.0], normal: [ 0.0,  0.0, -1.0] },
+        Vertex { position: [-1.0, -1.0, -1.0], color: [1.0, 0.0, 1.0], uv: [1.0, 1.0], normal: [ 0.0,  0.0, -1.0] },
+        Vertex { position: [-1.0,  1.0, -1.0], color: [0.0, 1.0, 1.0], uv: [1.0, 0.0], normal: [ 0.0,  0.0, -1.0] },
+        Vertex { position: [ 1.0,  1.0, -1.0], color: [1.0, 1.0, 1.0], uv: [0.0, 0.0], normal: [ 0.0,  0.0, -1.0] },
+        // Bottom face
+        Vertex { position: [-1.0, -1.0, -1.0], color: [1.0, 0.0, 1.0], uv: [0.0, 1.0], normal: [ 0.0, -1.0,  0.0] },
+        Vertex { position: [ 1.0, -1.0, -1.0], color: [0.5, 0.5, 0.5], uv: [1.0, 1.0], normal: [ 0.0, -1.0,  0.0] },
+        Vertex { position: [ 1.0, -1.0,  1.0], color: [0.0, 1.0, 0.0], uv: [1.0, 0.0], normal: [ 0.0, -1.0,  0.0] },
+        Vertex { position: [-1.0, -1.0,  1.0], color: [1.0, 0.0, 0.0], uv: [0.0, 0.0], normal: [ 0.0, -1.0,  0.0] },
+        // Top face
+        Vertex { position: [-1.0,  1.0,  1.0], color: [1.0, 1.0, 0.0], uv: [0.0, 1.0], normal: [ 0.0,  1.0,  0.0] },
+        Vertex { position: [ 1.0,  1.0,  1.0], color: [0.0, 0.0, 1.0], uv: [1.0, 1.0], normal: [ 0.0,  1.0,  0.0] },
+        Vertex { position: [ 1.0,  1.0, -1.0], color: [1.0, 1.0, 1.0], uv: [1.0, 0.0], normal: [ 0.0,  1.0,  0.0] },
+        Vertex { position: [-1.0,  1.0, -1.0], color: [0.0, 1.0, 1.0], uv: [0.0, 0.0], normal: [ 0.0,  1.0,  0.0] },
+        // Left face
+        Vertex { position: [-1.0, -1.0, -1.0], color: [1.0, 0.0, 1.0], uv: [0.0, 1.0], normal: [-1.0,  0.0,  0.0] },
+        Vertex { position: [-1.0, -1.0,  1.0], color: [1.0, 0.0, 0.0], uv: [1.0, 1.0], normal: [-1.0,  0.0,  0.0] },
+        Vertex { position: [-1.0,  1.0,  1.0], color: [1.0, 1.0, 0.0], uv: [1.0, 0.0], normal: [-1.0,  0.0,  0.0] },
+        Vertex { position: [-1.0,  1.0, -1.0], color: [0.0, 1.0, 1.0], uv: [0.0, 0.0], normal: [-1.0,  0.0,  0.0] },
+        // Right face
+        Vertex { position: [ 1.0, -1.0,  1.0], color: [0.0, 1.0, 0.0], uv: [0.0, 1.0], normal: [ 1.0,  0.0,  0.0] },
+        Vertex { position: [ 1.0, -1.0, -1.0], color: [0.5, 0.5, 0.5], uv: [1.0, 1.0], normal: [ 1.0,  0.0,  0.0] },
+        Vertex { position: [ 1.0,  1.0, -1.0], color: [1.0, 1.0, 1.0], uv: [1.0, 0.0], normal: [ 1.0,  0.0,  0.0] },
+        Vertex { position: [ 1.0,  1.0,  1.0], color: [0.0, 0.0, 1.0], uv: [0.0, 0.0], normal: [ 1.0,  0.0,  0.0] },
+    ]
+}
+
+fn create_cube_indices() -> Vec<u32> {
+    (0..6u32)
+        .flat_map(|face| [0, 1, 2, 2, 3, 0].map(|i| face * 4 + i))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_hits_nearest_cube_face() {
+        let triangles = Mesh::cube().triangles();
+        let t = intersect_triangles(&triangles, Vec3::new(0.2, 0.3, 5.0), Vec3::NEG_Z, 0.0..=f32::INFINITY);
+        assert_eq!(t, Some(4.0));
+        // From inside, the far side is hit
+        let t = intersect_triangles(&triangles, Vec3::ZERO, Vec3::X * 2.0, 0.0..=f32::INFINITY);
+        assert_eq!(t, Some(0.5));
+        // Past the near face, the far one is
+        let t = intersect_triangles(&triangles, Vec3::new(0.2, 0.3, 5.0), Vec3::NEG_Z, 5.0..=f32::INFINITY);
+        assert_eq!(t, Some(6.0));
+        let t = intersect_triangles(&triangles, Vec3::new(0.2, 0.3, 5.0), Vec3::NEG_Z, 0.0..=3.0);
+        assert_eq!(t, None);
+    }
+
+    #[test]
+    fn cube_bounding_sphere_passes_through_corners() {
+        let sphere = Mesh::cube().bounding_sphere().unwrap();
+        assert_eq!(sphere.center, Vec3::ZERO);
+        assert!((sphere.radius - 3.0_f32.sqrt()).abs() < 1e-6);
+        let moved = sphere.transformed(Mat4::from_scale_rotation_translation(Vec3::new(1.0, 3.0, 2.0), glam::Quat::from_rotation_z(1.0), Vec3::X));
+        assert!(moved.center.abs_diff_eq(Vec3::X, 1e-6));
+        assert!((moved.radius - 3.0 * 3.0_f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn obj_from_bytes_shares_vertices_and_fan_triangulates() {
+        let mesh = Mesh::load_obj_from_bytes(b"v 0 0 0\nv 1 0 0\nv 1 1 0 1 0 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[2].color, [1.0, 0.0, 0.0]);
+        assert!(Mesh::load_obj_from_bytes(b"v 0 0 0\xff\n").is_err());
+        assert!(Mesh::load_obj_from_bytes(b"v 0 0 0\nf 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn sample_model_parses_with_outward_normals() {
+        let mesh = Mesh::sample().unwrap();
+        assert_eq!(mesh.vertices.len(), 96 * 8);
+        // The knot winds around the origin, so vertices on its outer rim face away from it
+        let outer = mesh.vertices.iter().max_by(|a, b| Vec3::from(a.position).length().total_cmp(&Vec3::from(b.position).length())).unwrap();
+        assert!(Vec3::from(outer.normal).dot(Vec3::from(outer.position)) > 0.0);
+    }
+
+    #[test]
+    fn reversed_triangle_is_flipped_to_match_its_normals() {
+        let vertex = |position: [f32; 3]| Vertex { position, color: DEFAULT_COLOR, uv: [0.0; 2], normal: [0.0, 0.0, 1.0] };
+        let mut mesh = Mesh {
+            vertices: vec![vertex([0.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0]), vertex([0.0, 1.0, 0.0]), vertex([1.0, 1.0, 0.0])],
+            // The first triangle winds counter-clockwise seen from +Z, the second clockwise
+            indices: vec![0, 1, 2, 1, 2, 3],
+        };
+        assert_eq!(mesh.fix_winding(), 1);
+        assert_eq!(mesh.indices, [0, 1, 2, 1, 3, 2]);
+        assert!(mesh.vertices.iter().all(|v| v.normal == [0.0, 0.0, 1.0]));
+        assert_eq!(mesh.fix_winding(), 0);
+    }
+
+    #[test]
+    fn normals_average_adjacent_faces() {
+        // Two triangles folded along the X axis, one facing +Z and one +Y
+        let vertex = |position: [f32; 3]| Vertex { position, color: DEFAULT_COLOR, uv: [0.0; 2], normal: [0.0; 3] };
+        let mut mesh = Mesh {
+            vertices: vec![vertex([0.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0]), vertex([0.0, 1.0, 0.0]), vertex([0.0, 0.0, -1.0])],
+            indices: vec![0, 1, 2, 0, 1, 3],
+        };
+        mesh.compute_normals();
+        let normals: Vec<Vec3> = mesh.vertices.iter().map(|v| Vec3::from(v.normal)).collect();
+        assert!(normals[0].abs_diff_eq(Vec3::new(0.0, 1.0, 1.0).normalize(), 1e-6));
+        assert_eq!(normals[2], Vec3::Z);
+        assert_eq!(normals[3], Vec3::Y);
+    }
+
+    /// Unit cube, one facet per line
+    const STL_CUBE: &str = "solid cube
+        facet normal 0 0 -1 outer loop vertex 0 0 0 vertex 0 1 0 vertex 1 1 0 endloop endfacet
+        facet normal 0 0 -1 outer loop vertex 0 0 0 vertex 1 1 0 vertex 1 0 0 endloop endfacet
+        facet normal 0 0 1 outer loop vertex 0 0 1 vertex 1 0 1 vertex 1 1 1 endloop endfacet
+        facet normal 0 0 1 outer loop vertex 0 0 1 vertex 1 1 1 vertex 0 1 1 endloop endfacet
+        facet normal 0 -1 0 outer loop vertex 0 0 0 vertex 1 0 0 vertex 1 0 1 endloop endfacet
+        facet normal 0 -1 0 outer loop vertex 0 0 0 vertex 1 0 1 vertex 0 0 1 endloop endfacet
+        facet normal 0 1 0 outer loop vertex 0 1 0 vertex 0 1 1 vertex 1 1 1 endloop endfacet
+        facet normal 0 1 0 outer loop vertex 0 1 0 vertex 1 1 1 vertex 1 1 0 endloop endfacet
+        facet normal -1 0 0 outer loop vertex 0 0 0 vertex 0 0 1 vertex 0 1 1 endloop endfacet
+        facet normal -1 0 0 outer loop vertex 0 0 0 vertex 0 1 1 vertex 0 1 0 endloop endfacet
+        facet normal 1 0 0 outer loop vertex 1 0 0 vertex 1 1 0 vertex 1 1 1 endloop endfacet
+        facet normal 1 0 0 outer loop vertex 1 0 0 vertex 1 1 1 vertex 1 0 1 endloop endfacet
+        endsolid cube
+    ";
+
+    #[test]
+    fn ascii_stl_cube_is_flat_shaded() {
+        let mesh = Mesh::load_stl_from_bytes(STL_CUBE.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices.len(), 36);
+        assert_eq!(mesh.indices.len(), 36);
+        assert_eq!(mesh.bounds(), Some(Aabb { min: Vec3::ZERO, max: Vec3::ONE }));
+        // Every corner carries its facet's normal, which agrees with the winding
+        for triangle in mesh.vertices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(triangle[i].position));
+            let normal = Vec3::from(triangle[0].normal);
+            assert_eq!((b - a).cross(c - a).normalize(), normal);
+            assert!(triangle.iter().all(|v| Vec3::from(v.normal) == normal));
+        }
+    }
+
+    #[test]
+    fn binary_stl_fills_in_missing_normals() {
+        // A header starting with "solid" must not be mistaken for ASCII
+        let mut bytes = b"solid but binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend(1u32.to_le_bytes());
+        let floats = [[0.0; 3], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        bytes.extend(floats.iter().flatten().flat_map(|f: &f32| f.to_le_bytes()));
+        bytes.extend([0, 0]);
+        let mesh = Mesh::load_stl_from_bytes(&bytes).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.vertices[2].position, [0.0, 1.0, 0.0]);
+        assert_eq!(mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert!(Mesh::load_stl_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn degenerate_triangles_do_not_produce_nans() {
+        let vertex = |position: [f32; 3]| Vertex { position, color: DEFAULT_COLOR, uv: [0.0; 2], normal: [0.0; 3] };
+        // A proper triangle facing +Z sharing vertex 0 with a collinear one and
+        // one with a repeated corner; vertex 4 is only in degenerate triangles
+        let mut mesh = Mesh {
+            vertices: vec![vertex([0.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0]), vertex([0.0, 1.0, 0.0]), vertex([2.0, 0.0, 0.0]), vertex([5.0, 5.0, 5.0])],
+            indices: vec![0, 1, 2, 0, 1, 3, 0, 4, 4],
+        };
+        mesh.compute_normals();
+        assert!(mesh.vertices.iter().all(|v| Vec3::from(v.normal).is_finite()));
+        assert_eq!(mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(mesh.vertices[3].normal, FALLBACK_NORMAL.to_array());
+        assert_eq!(mesh.vertices[4].normal, FALLBACK_NORMAL.to_array());
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let triangles = Mesh::triangle().triangles();
+        assert_eq!(intersect_triangles(&triangles, Vec3::new(0.9, 0.9, 1.0), Vec3::NEG_Z, 0.0..=f32::INFINITY), None);
+        assert_eq!(intersect_triangles(&triangles, Vec3::new(0.0, 0.0, 1.0), Vec3::Z, 0.0..=f32::INFINITY), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Occlusion culling: each frame the objects' bounding boxes are drawn
+//! against the depth buffer inside occlusion queries, and objects whose box
+//! had no visible samples are skipped the next frame.
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::mesh::{Aabb, Mesh};
+use crate::scene::MeshBuffers;
+
+const RESULT_SIZE: wgpu::BufferAddress = std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+pub struct OcclusionCulling {
+    pub query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Unit cube drawn, scaled to each object's bounds, inside the queries
+    bounds_mesh: MeshBuffers,
+    /// Per object: whether its bounds were visible in the latest results
+    visible: Vec<bool>,
+    /// Objects whose results are being copied back, and whether the mapping has completed
+    in_flight: Option<(Range<usize>, Arc<AtomicBool>)>,
+    map_requested: bool,
+}
+
+impl OcclusionCulling {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let count = capacity.max(1);
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Occlusion Query Set"),
+            ty: wgpu::QueryType::Occlusion,
+            count: count as u32,
+        });
+        let size = RESULT_SIZE * count as wgpu::BufferAddress;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            bounds_mesh: MeshBuffers::new(device, "Occlusion Bounds", &Mesh::cube()),
+            visible: vec![true; capacity],
+            in_flight: None,
+            map_requested: false,
+        }
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.visible[index]
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible.iter().filter(|&&visible| visible).count()
+    }
+
+    /// Forgets previous results, e.g. when culling is switched back on.
+    pub fn reset(&mut self) {
+        self.visible.fill(true);
+    }
+
+    /// Model matrix that stretches the unit cube over `bounds`.
+    pub fn bounds_matrix(bounds: &Aabb, world: Mat4) -> Mat4 {
+        world * Mat4::from_scale_rotation_translation(bounds.size() * 0.5, Quat::IDENTITY, bounds.center())
+            // Pad slightly so flat objects don't z-fight with their own box
+            * Mat4::from_scale(Vec3::splat(1.001))
+    }
+
+    /// Draws object `index`'s bounds inside its query. The pipeline and the
+    /// object's bind group must already be set.
+    pub fn query<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, index: usize) {
+        render_pass.begin_occlusion_query(index as u32);
+        self.bounds_mesh.draw(render_pass);
+        render_pass.end_occlusion_query();
+    }
+
+    /// Copies this frame's results for `queried` towards the CPU, unless the
+    /// previous frame's copy hasn't been read yet.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder, queried: Range<usize>) {
+        if self.in_flight.is_some() || queried.is_empty() {
+            return;
+        }
+        let bytes = byte_range(&queried);
+        encoder.resolve_query_set(&self.query_set, queried.start as u32..queried.end as u32, &self.resolve_buffer, bytes.start);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, bytes.start, &self.readback_buffer, bytes.start, bytes.end - bytes.start);
+        self.in_flight = Some((queried, Arc::new(AtomicBool::new(false))));
+    }
+
+    /// Starts mapping the results resolved this frame; call after submitting.
+    pub fn map_results(&mut self) {
+        let Some((queried, mapped)) = &self.in_flight else { return };
+        if std::mem::replace(&mut self.map_requested, true) {
+            return;
+        }
+        let mapped = mapped.clone();
+        self.readback_buffer.slice(byte_range(queried)).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                mapped.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    /// Takes in results that have finished mapping. Returns whether any
+    /// object's visibility changed.
+    pub fn poll(&mut self, device: &wgpu::Device) -> bool {
+        device.poll(wgpu::Maintain::Poll);
+        let Some((queried, mapped)) = &self.in_flight else { return false };
+        if !mapped.load(Ordering::Acquire) {
+            return false;
+        }
+        let mut changed = false;
+        {
+            let data = self.readback_buffer.slice(byte_range(queried)).get_mapped_range();
+            let samples: &[u64] = bytemuck::cast_slice(&data);
+            for (visible, &count) in self.visible[queried.clone()].iter_mut().zip(samples) {
+                changed |= *visible != (count > 0);
+                *visible = count > 0;
+            }
+        }
+        self.readback_buffer.unmap();
+        self.in_flight = None;
+        self.map_requested = false;
+        changed
+    }
+}
+
+/// Bytes holding the results of the queries in `queries`.
+fn byte_range(queries: &Range<usize>) -> Range<wgpu::BufferAddress> {
+    queries.start as wgpu::BufferAddress * RESULT_SIZE..queries.end as wgpu::BufferAddress * RESULT_SIZE
+}
@@ -0,0 +1,174 @@
+//! Overdraw visualization: objects are drawn into an offscreen count target
+//! with additive blending and no depth test, so every fragment adds to its
+//! pixel's count, and a fullscreen pass shows the counts as a heat map.
+
+use crate::shaders::OVERDRAW_SHADER;
+use crate::vertex::VertexLayout;
+
+const HEAT_SHADER: &str = include_str!("shaders/heat.wgsl");
+
+/// Offscreen count target and the passes around it; recreate it when the
+/// target size or format changes.
+pub struct Overdraw {
+    _texture: wgpu::Texture,
+    count_view: wgpu::TextureView,
+    /// Counting pipelines with back faces culled and, for double-sided
+    /// objects, kept
+    count_pipelines: [wgpu::RenderPipeline; 2],
+    heat_bind_group: wgpu::BindGroup,
+    heat_pipeline: wgpu::RenderPipeline,
+}
+
+impl Overdraw {
+    /// `scene_layouts` are the scene's uniform and object bind group layouts,
+    /// which the counting pipelines share with the gallery pipelines.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        scene_layouts: &[&wgpu::BindGroupLayout],
+        vertex_layout: &VertexLayout,
+    ) -> Self {
+        // 8-bit channels blend additively everywhere; see HEAT_STEP in heat.wgsl
+        let count_format = wgpu::TextureFormat::Rgba8Unorm;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Overdraw Count Texture"),
+            size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: count_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let count_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let count_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(OVERDRAW_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(OVERDRAW_SHADER.source().into()),
+        });
+        let count_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overdraw Count Pipeline Layout"),
+            bind_group_layouts: scene_layouts,
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let count_pipelines = [Some(wgpu::Face::Back), None].map(|cull_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Overdraw Count Pipeline"),
+                layout: Some(&count_layout),
+                vertex: wgpu::VertexState {
+                    module: &count_shader,
+                    entry_point: "vs_main",
+                    buffers: &[vertex_layout.buffer_layout()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &count_shader,
+                    entry_point: OVERDRAW_SHADER.entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: count_format,
+                        blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState { cull_mode, ..Default::default() },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        });
+
+        let heat_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("heat_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let heat_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("heat_bind_group"),
+            layout: &heat_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&count_view) }],
+        });
+        let heat_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("heat"),
+            source: wgpu::ShaderSource::Wgsl(HEAT_SHADER.into()),
+        });
+        let heat_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Heat Pipeline Layout"),
+            bind_group_layouts: &[&heat_layout],
+            push_constant_ranges: &[],
+        });
+        let heat_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Heat Pipeline"),
+            layout: Some(&heat_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &heat_shader,
+                entry_point: "vs_heat",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &heat_shader,
+                entry_point: "fs_heat",
+                targets: &[Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self { _texture: texture, count_view, count_pipelines, heat_bind_group, heat_pipeline }
+    }
+
+    /// Begins the counting pass, starting from zero everywhere. Draw the
+    /// objects into it with `pipeline()` and the scene's bind groups.
+    pub fn begin_count<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overdraw Count Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.count_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    pub fn pipeline(&self, double_sided: bool) -> &wgpu::RenderPipeline {
+        &self.count_pipelines[double_sided as usize]
+    }
+
+    /// Records the pass replacing `output` with the heat map of the counts.
+    pub fn draw_heat(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Heat Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.heat_pipeline);
+        pass.set_bind_group(0, &self.heat_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,58 @@
+//! Frame pacing: caps the redraw rate instead of drawing as fast as the event loop spins.
+
+use std::time::{Duration, Instant};
+
+/// Assumed refresh rate when the monitor doesn't report one.
+pub const FALLBACK_REFRESH_HZ: f32 = 60.0;
+
+pub struct FramePacer {
+    interval: Duration,
+    next_frame: Instant,
+}
+
+impl FramePacer {
+    pub fn new(max_fps: f32, now: Instant) -> Self {
+        Self { interval: Duration::from_secs_f32(1.0 / max_fps), next_frame: now }
+    }
+
+    /// When the next frame is due.
+    pub fn next_frame(&self) -> Instant {
+        self.next_frame
+    }
+
+    /// Records a frame drawn at `now` and schedules the next one.
+    pub fn frame_presented(&mut self, now: Instant) {
+        // Keep to the frame grid while on time, but don't try to catch up after a stall
+        self.next_frame = (self.next_frame + self.interval).max(now);
+    }
+}
+
+/// Refresh rate of the window's monitor in Hz, if the platform reports one.
+pub fn monitor_refresh_rate(window: &winit::window::Window) -> Option<f32> {
+    let millihertz = window.current_monitor()?.refresh_rate_millihertz()?;
+    Some(millihertz as f32 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_follow_the_interval() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(50.0, start);
+        pacer.frame_presented(start);
+        assert_eq!(pacer.next_frame(), start + Duration::from_millis(20));
+        pacer.frame_presented(start + Duration::from_millis(21));
+        assert_eq!(pacer.next_frame(), start + Duration::from_millis(40));
+    }
+
+    #[test]
+    fn stalls_are_not_caught_up() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(50.0, start);
+        let late = start + Duration::from_millis(500);
+        pacer.frame_presented(late);
+        assert_eq!(pacer.next_frame(), late);
+    }
+}
@@ -0,0 +1,205 @@
+//! 360° panoramas: the scene is rendered into the six faces of a cubemap from
+//! the camera's position, and a fullscreen pass reprojects the cubemap into
+//! an equirectangular image, world-aligned with -Z at its center and +Y up.
+
+use glam::{Mat3, Quat, Vec3};
+
+use crate::texture::Image;
+
+const PANORAMA_SHADER: &str = include_str!("shaders/panorama.wgsl");
+
+/// Edge length of each cube face in pixels; the panorama is four faces wide
+/// and two high, which keeps about one texel per pixel along the horizon.
+pub const FACE_SIZE: u32 = 512;
+
+/// View rotations for the cube faces, in layer order. Cubemap lookups use a
+/// left-handed convention, so rendering the faces as they are sampled would
+/// need mirrored cameras (and flipped triangle winding). Instead each face
+/// sees the world mirrored in X, and the reprojection pass mirrors its lookup
+/// direction back.
+pub fn face_rotations() -> [Quat; 6] {
+    // Forward, right and up of each face once mirrored
+    [
+        (-Vec3::X, -Vec3::Z, Vec3::Y),
+        (Vec3::X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, -Vec3::X, -Vec3::Z),
+        (-Vec3::Y, -Vec3::X, Vec3::Z),
+        (Vec3::Z, -Vec3::X, Vec3::Y),
+        (-Vec3::Z, Vec3::X, Vec3::Y),
+    ]
+    .map(|(forward, right, up)| Quat::from_mat3(&Mat3::from_cols(right, up, -forward)).inverse())
+}
+
+/// A square texture with a layer per cube face, rendered into one layer at a time.
+pub fn create_cube(device: &wgpu::Device, format: wgpu::TextureFormat, face_size: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Panorama Cube Texture"),
+        size: wgpu::Extent3d { width: face_size, height: face_size, depth_or_array_layers: 6 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+/// A view of one cube face, to render into.
+pub fn face_view(cube: &wgpu::Texture, layer: u32) -> wgpu::TextureView {
+    cube.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        base_array_layer: layer,
+        array_layer_count: Some(1),
+        ..Default::default()
+    })
+}
+
+/// Reprojects the cube from `create_cube` into an equirectangular image
+/// twice as wide as it is high, and reads it back.
+pub fn reproject(device: &wgpu::Device, queue: &wgpu::Queue, cube: &wgpu::Texture) -> Result<Image, String> {
+    let face_size = cube.width();
+    let format = cube.format();
+    let output = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Panorama Texture"),
+        size: wgpu::Extent3d { width: face_size * 4, height: face_size * 2, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let cube_view = cube.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    // Filtering across face edges is seamless for cube views
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Panorama Sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("panorama_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("panorama_bind_group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&cube_view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+        ],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("panorama"),
+        source: wgpu::ShaderSource::Wgsl(PANORAMA_SHADER.into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Panorama Pipeline Layout"),
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Panorama Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_panorama",
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_panorama",
+            targets: &[Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Panorama Encoder") });
+    {
+        let view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Panorama Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+    Image::read_back(device, queue, &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    /// Layer and texture coordinates a cubemap lookup of `direction` reads,
+    /// per the WebGPU spec's face selection table.
+    fn cube_texel(direction: Vec3) -> (usize, Vec2) {
+        let Vec3 { x, y, z } = direction;
+        let (layer, major, s, t) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+            if x > 0.0 { (0, x, -z, -y) } else { (1, -x, z, -y) }
+        } else if y.abs() >= z.abs() {
+            if y > 0.0 { (2, y, x, z) } else { (3, -y, x, -z) }
+        } else if z > 0.0 {
+            (4, z, x, -y)
+        } else {
+            (5, -z, -x, -y)
+        };
+        (layer, (Vec2::new(s, t) / major + 1.0) / 2.0)
+    }
+
+    #[test]
+    fn faces_render_what_the_cube_lookup_reads() {
+        let rotations = face_rotations();
+        for direction in [
+            Vec3::new(1.0, 0.2, -0.3),
+            Vec3::new(-0.5, -0.9, 0.1),
+            Vec3::new(0.3, 0.4, 1.0),
+            Vec3::new(0.1, 0.2, -1.0),
+            Vec3::new(0.2, 1.0, 0.6),
+        ] {
+            // The shader looks up the mirrored direction
+            let (layer, texel) = cube_texel(direction * Vec3::new(-1.0, 1.0, 1.0));
+            // Where a 90° camera with this face's rotation shows `direction`
+            let view = rotations[layer] * direction;
+            assert!(view.z < 0.0);
+            let ndc = Vec2::new(view.x, view.y) / -view.z;
+            let rendered = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) / 2.0;
+            assert!((rendered - texel).length() < 1e-5, "{direction}: {rendered} != {texel}");
+        }
+    }
+}
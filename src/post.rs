@@ -0,0 +1,240 @@
+//! Fullscreen post-processing. While an effect is on, the scene is rendered
+//! into an offscreen target, and a fullscreen pass reads it to produce the
+//! final frame. Motion blur blends each frame with the previous output, kept
+//! in a pair of history textures that swap roles every frame. Depth of field
+//! blurs each pixel by its distance from the focal plane, read from the depth
+//! buffer.
+
+const POST_SHADER: &str = include_str!("shaders/post.wgsl");
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PostUniforms {
+    /// Weight of the previous output, 0 for none
+    pub motion_blur: f32,
+    /// Depth of field; an aperture of 0 turns it off
+    pub focal_distance: f32,
+    pub aperture: f32,
+    /// Camera clip planes, to turn depth buffer values back into distances
+    pub near: f32,
+    pub far: f32,
+    pub _padding: [f32; 3],
+}
+
+/// Thin-lens style focus: pixels at `focal_distance` are sharp, and the blur
+/// radius grows towards `aperture` pixels for ones infinitely far behind it
+/// (and faster for ones in front of it, up to a limit in the shader).
+#[derive(Debug)]
+pub struct DepthOfField {
+    pub enabled: bool,
+    /// View-space distance in front of the camera, in world units
+    pub focal_distance: f32,
+    /// Blur radius in pixels
+    pub aperture: f32,
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self { enabled: false, focal_distance: 5.0, aperture: 8.0 }
+    }
+}
+
+/// A color texture that is both rendered into and sampled.
+struct Target {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl Target {
+    fn new(device: &wgpu::Device, label: &str, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { _texture: texture, view }
+    }
+}
+
+/// Offscreen targets and the fullscreen pass; recreate it when the target size or format changes.
+pub struct PostProcess {
+    scene: Target,
+    history: [Target; 2],
+    /// Index of the history texture read this frame; the other one is written
+    current: usize,
+    /// Cleared when the history holds no earlier frame yet, e.g. right after creation
+    history_valid: bool,
+    uniform_buffer: wgpu::Buffer,
+    /// `bind_groups[i]` reads `history[i]`
+    bind_groups: [wgpu::BindGroup; 2],
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcess {
+    /// `depth` is the scene's depth buffer, multisampled if `sample_count` is above 1.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        depth: &wgpu::Texture,
+        sample_count: u32,
+    ) -> Self {
+        let scene = Target::new(device, "Post Scene Texture", format, width, height);
+        let history = [0, 1].map(|i| Target::new(device, &format!("Post History Texture {i}"), format, width, height));
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Uniform Buffer"),
+            size: std::mem::size_of::<PostUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: sample_count > 1,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("post_bind_group"),
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&scene.view) },
+                    wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&history[i].view) },
+                    wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(&sampler) },
+                    wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::TextureView(&depth_view) },
+                ],
+            })
+        });
+        // `textureLoad` takes a mip level for one and a sample index for the
+        // other, so the shader reads both depth texture types unchanged
+        let source = if sample_count > 1 {
+            POST_SHADER.replace("scene_depth: texture_2d<f32>", "scene_depth: texture_multisampled_2d<f32>")
+        } else {
+            POST_SHADER.to_string()
+        };
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("post"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let target = Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Post Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_post",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_post",
+                // The frame, and the history texture the next frame reads
+                targets: &[target.clone(), target],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self { scene, history, current: 0, history_valid: false, uniform_buffer, bind_groups, pipeline }
+    }
+
+    /// Where the scene is rendered (or resolved) to while post-processing is on.
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene.view
+    }
+
+    /// Records the fullscreen pass that writes the final frame to `output`.
+    /// Call `advance()` once the frame has been submitted.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, output: &wgpu::TextureView, mut uniforms: PostUniforms) {
+        if !self.history_valid {
+            uniforms.motion_blur = 0.0;
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+        let attachment = |view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post Pass"),
+            color_attachments: &[attachment(output), attachment(&self.history[1 - self.current].view)],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Makes this frame's output the history of the next one.
+    pub fn advance(&mut self) {
+        self.current = 1 - self.current;
+        self.history_valid = true;
+    }
+}
@@ -0,0 +1,93 @@
+//! Recovery from failures to acquire swapchain frames, e.g. during a long
+//! resize storm: failed frames are skipped and retried with a growing delay,
+//! and the surface is reconfigured from scratch if they keep failing.
+
+use std::time::{Duration, Instant};
+
+/// The surface is reconfigured after every this many consecutive failures.
+pub const RECONFIGURE_AFTER: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(5);
+const MAX_BACKOFF: Duration = Duration::from_millis(500);
+
+/// What to do after a failed frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Recovery {
+    /// Skip the frame and try again after the delay
+    Retry(Duration),
+    /// Reconfigure the surface, then try again after the delay
+    Reconfigure(Duration),
+}
+
+#[derive(Debug, Default)]
+pub struct PresentRetry {
+    consecutive_failures: u32,
+    /// Frames skipped since the last successful one
+    skipped_frames: u32,
+    retry_at: Option<Instant>,
+}
+
+impl PresentRetry {
+    /// Records a frame that could not be acquired at `now`.
+    pub fn failed(&mut self, now: Instant) -> Recovery {
+        self.consecutive_failures += 1;
+        self.skipped_frames += 1;
+        let delay = INITIAL_BACKOFF.saturating_mul(1 << (self.consecutive_failures - 1).min(16)).min(MAX_BACKOFF);
+        self.retry_at = Some(now + delay);
+        if self.consecutive_failures.is_multiple_of(RECONFIGURE_AFTER) {
+            Recovery::Reconfigure(delay)
+        } else {
+            Recovery::Retry(delay)
+        }
+    }
+
+    /// Records a presented frame and returns the number of frames skipped
+    /// before it, if any.
+    pub fn succeeded(&mut self) -> Option<u32> {
+        self.consecutive_failures = 0;
+        self.retry_at = None;
+        let skipped = std::mem::take(&mut self.skipped_frames);
+        (skipped > 0).then_some(skipped)
+    }
+
+    /// When to try again after a failure; `None` while frames succeed.
+    pub fn retry_at(&self) -> Option<Instant> {
+        self.retry_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_then_reconfigures() {
+        let now = Instant::now();
+        let mut retry = PresentRetry::default();
+        let recoveries: Vec<Recovery> = (0..RECONFIGURE_AFTER + 1).map(|_| retry.failed(now)).collect();
+        assert_eq!(recoveries[0], Recovery::Retry(INITIAL_BACKOFF));
+        assert_eq!(recoveries[1], Recovery::Retry(INITIAL_BACKOFF * 2));
+        assert_eq!(recoveries[RECONFIGURE_AFTER as usize - 1], Recovery::Reconfigure(INITIAL_BACKOFF * 16));
+        assert_eq!(recoveries[RECONFIGURE_AFTER as usize], Recovery::Retry(INITIAL_BACKOFF * 32));
+        assert_eq!(retry.retry_at(), Some(now + INITIAL_BACKOFF * 32));
+    }
+
+    #[test]
+    fn success_resets_and_reports_skipped_frames() {
+        let mut retry = PresentRetry::default();
+        assert_eq!(retry.succeeded(), None);
+        retry.failed(Instant::now());
+        retry.failed(Instant::now());
+        assert_eq!(retry.succeeded(), Some(2));
+        assert_eq!(retry.retry_at(), None);
+        assert_eq!(retry.failed(Instant::now()), Recovery::Retry(INITIAL_BACKOFF));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let mut retry = PresentRetry::default();
+        for _ in 0..100 {
+            let (Recovery::Retry(delay) | Recovery::Reconfigure(delay)) = retry.failed(Instant::now());
+            assert!(delay <= MAX_BACKOFF);
+        }
+    }
+}
@@ -0,0 +1,2475 @@
+//! The renderer behind the window: owns the device, pipelines and scene,
+//! and draws into any texture view.
+
+use winit::keyboard::KeyCode;
+use wgpu::{Instance, Device, Queue, SurfaceConfiguration, util::DeviceExt};
+use glam::{Vec2, Vec3, Vec4, Mat4, Quat};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::bench::FrameStats;
+use crate::clock::AnimationClock;
+use crate::color_space::{self, OutputEncoding};
+use crate::config::{CameraMode, ColorLoad, Config, DepthLoad, FrustumCulling, StartScene, DEFAULT_CLEAR_COLOR};
+use crate::environment::Environment;
+use crate::frustum::{self, Frustum};
+use crate::hud::{Hud, Readout};
+use crate::inertia::Inertia;
+use crate::input::InputEvent;
+use crate::jitter;
+use crate::lights::{LightUniforms, PointLight, MAX_LIGHTS};
+use crate::mesh::{Aabb, Mesh};
+use crate::occlusion::OcclusionCulling;
+use crate::overdraw::Overdraw;
+use crate::panorama;
+use crate::post::{DepthOfField, PostProcess, PostUniforms};
+use crate::scene::{self, MeshBuffers, ObjectBuffer, ObjectUniforms, RenderObject, ShadingModel};
+use crate::showcase::IdleOrbit;
+use crate::shaders::{ShaderVariant, LINE_SHADER, OUTLINE_SHADER, SHADER_VARIANTS, TRIANGLE_SHADER, WIREFRAME_SHADER};
+use crate::texture::{Image, Texture, TextureLoader};
+use crate::trackball::{TrackballDrag, level_roll};
+use crate::uniform_ring::{self, UniformRing};
+use crate::vertex::{Vertex, VertexLayout};
+
+/// Per-frame callback, given the camera and the seconds since the last frame
+type TickFn = Box<dyn FnMut(&mut Camera, f32)>;
+
+/// Renders the scene into the window's surface, or without one into
+/// offscreen targets and texture views given to `render_into`.
+#[derive(Default)]
+pub struct Renderer {
+    /// Presents to the window, `None` when rendering offscreen only
+    surface: Option<wgpu::Surface<'static>>,
+    instance: Option<Instance>,
+    adapter: Option<wgpu::Adapter>,
+    device: Option<Device>,
+    queue: Option<Queue>,
+    config: Option<SurfaceConfiguration>,
+    camera: Camera,
+    mouse_pressed: bool,
+    /// One pipeline per entry in `SHADER_VARIANTS`
+    pipelines: Vec<wgpu::RenderPipeline>,
+    /// Same as `pipelines` but without back-face culling, for double-sided objects
+    double_sided_pipelines: Vec<wgpu::RenderPipeline>,
+    outline_pipelines: Option<OutlinePipelines>,
+    /// Draws bounding boxes into occlusion queries without touching color or depth
+    bounds_pipeline: Option<wgpu::RenderPipeline>,
+    /// Draws line lists on top of the scene
+    line_pipeline: Option<wgpu::RenderPipeline>,
+    /// Like `line_pipeline`, but hidden behind the scene's surfaces
+    depth_tested_line_pipeline: Option<wgpu::RenderPipeline>,
+    /// Count target and passes of the overdraw view, `Some` while it's shown
+    overdraw: Option<Overdraw>,
+    /// Like `line_pipeline`, but for lines in screen space; their object
+    /// matrix maps straight to clip space
+    screen_line_pipeline: Option<wgpu::RenderPipeline>,
+    /// Arms of the center reticle, in pixels around the window center
+    crosshair: Option<MeshBuffers>,
+    show_crosshair: bool,
+    /// Draws triangle edges only; `None` without `POLYGON_MODE_LINE`
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    /// Draw all objects with `wireframe_pipeline` instead of the active shader
+    wireframe: bool,
+    wireframe_colors: WireframeColors,
+    material: ProceduralMaterial,
+    /// Colors every triangle differently; `None` without `SHADER_PRIMITIVE_INDEX`
+    triangle_pipeline: Option<wgpu::RenderPipeline>,
+    /// Draw all objects with `triangle_pipeline` instead of the active shader
+    triangle_colors: bool,
+    /// Conversion the shaders apply for the color target format and `--color-space`
+    output_encoding: OutputEncoding,
+    /// Color target format `pipelines` were built for
+    pipeline_format: Option<wgpu::TextureFormat>,
+    active_shader: usize,
+    objects: Vec<RenderObject>,
+    object_buffer: Option<ObjectBuffer>,
+    /// Show only `objects[active_object]` instead of all objects side by side
+    carousel: bool,
+    active_object: usize,
+    selected: Option<usize>,
+    cursor_position: Option<Vec2>,
+    /// Mouse motion since the left button went down, to tell clicks from
+    /// drags; `None` while the button is up
+    drag_distance: Option<f32>,
+    /// Trackball drag in progress and where the cursor would be had it not
+    /// been held in place
+    trackball_drag: Option<(TrackballDrag, Vec2)>,
+    /// Per-frame `Uniforms`, a slot further each frame
+    uniform_ring: Option<UniformRing>,
+    light_buffer: Option<wgpu::Buffer>,
+    lights: Vec<PointLight>,
+    depth_texture: Option<wgpu::Texture>,
+    depth_view: Option<wgpu::TextureView>,
+    /// Multisampled color target resolved into the frame; `None` without MSAA
+    msaa_view: Option<wgpu::TextureView>,
+    /// Whether `render_into` has drawn into the current frame's target yet;
+    /// until it has, the target's contents are undefined and can't be loaded.
+    /// Cleared by `begin_frame`
+    target_written: bool,
+    /// Offscreen scene target and fullscreen pass, `Some` while a post effect is on
+    post: Option<PostProcess>,
+    /// Weight of the previous frame in the blend while motion blur is on
+    motion_blur: f32,
+    motion_blur_enabled: bool,
+    depth_of_field: DepthOfField,
+    /// Camera and frame time readout in the top-left corner
+    hud: Option<Hud>,
+    show_hud: bool,
+    /// Time between the last two frames, for the HUD
+    frame_time: Duration,
+    /// Start of the current frame, as given to `advance`
+    frame_clock: Option<Instant>,
+    uniform_bind_group: Option<wgpu::BindGroup>,
+    settings: Config,
+    pipeline_stats: Option<PipelineStats>,
+    startup: Option<StartupTimer>,
+    /// Orbits the camera after `--showcase` seconds without input
+    showcase: Option<IdleOrbit>,
+    /// Custom per-frame logic, see `on_tick`
+    tick: Option<TickFn>,
+    /// Time driving the showcase orbit and the tick callback; input and
+    /// inertia run on real time
+    animation_clock: AnimationClock,
+    frame_index: u32,
+    environment: Option<Environment>,
+    /// Base color texture, sampled by the textured shader: white without
+    /// `--texture`, a placeholder while it loads
+    base_texture: Option<Texture>,
+    texture_sampler: Option<wgpu::Sampler>,
+    /// Height map sampled by the vertex stage: black (no displacement)
+    /// without `--displacement`
+    displacement_texture: Option<Texture>,
+    /// Vertex offset along the normal for the maximum height, in object units.
+    /// Object bounds don't account for it, so culling and picking ignore it
+    displacement_scale: f32,
+    texture_loader: Option<TextureLoader>,
+    /// Mix in reflections of the environment cubemap
+    reflective: bool,
+    display: DisplayAdjustment,
+    clip_plane: ClipPlane,
+    /// View-projection objects are culled against; stops following the
+    /// camera while culling is frozen
+    cull_view_proj: Mat4,
+    /// Outline of the frozen culling frustum, `Some` while frozen
+    frozen_frustum: Option<MeshBuffers>,
+    measurement: Measurement,
+    occlusion: Option<OcclusionCulling>,
+    /// Ignore the scroll wheel, so a stray scroll can't move the camera while
+    /// composing a screenshot
+    zoom_locked: bool,
+    /// Scroll dolly velocity with `--inertia`; instant otherwise
+    dolly: Inertia,
+}
+
+const LOD_DEMO_SPHERES: usize = 5;
+/// `(camera distance, sphere segments)` of the lower-detail meshes in `--lod-demo`
+const LOD_DEMO_LEVELS: [(f32, u32); 3] = [(12.0, 32), (20.0, 16), (32.0, 8)];
+
+/// Camera movement per scrolled line
+const ZOOM_SPEED: f32 = 0.5;
+/// With `--smart-speed`, the fraction of the distance to the surface ahead moved per scrolled line
+const SMART_SPEED_FRACTION: f32 = 0.1;
+const MIN_SMART_SPEED: f32 = 0.001;
+const MAX_SMART_SPEED: f32 = 100.0;
+
+/// Crosshair arm length in pixels and color, unless set with `--crosshair-size` and `--crosshair-color`
+const DEFAULT_CROSSHAIR_SIZE: f32 = 8.0;
+const DEFAULT_CROSSHAIR_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// Cursor movement in pixels below which a press and release count as a click
+const CLICK_TOLERANCE: f32 = 4.0;
+
+/// Extra room left around framed objects
+const FRAMING_MARGIN: f32 = 1.1;
+
+/// A perspective camera; the view rotates the world by `rotation`, then
+/// moves it by `-position`.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// Vertical field of view in radians
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 0.0, 5.0),
+            rotation: Quat::IDENTITY,
+            fov: 45.0_f32.to_radians(),
+            aspect: 1.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_translation(-self.position) * Mat4::from_quat(self.rotation)
+    }
+
+    /// The view matrix rotates the world before translating it, so the eye sits
+    /// at the inverse rotation of `position`.
+    pub fn world_position(&self) -> Vec3 {
+        self.rotation.inverse() * self.position
+    }
+
+    /// Backs the camera away from the origin until a sphere of `radius` around it fits the view.
+    fn frame(&mut self, radius: f32) {
+        let distance = radius * FRAMING_MARGIN / (self.fov * 0.5).sin();
+        self.position = Vec3::new(0.0, 0.0, distance);
+        self.far = (distance + radius) * 2.0;
+        self.near = self.far / 1000.0;
+    }
+
+    /// World-space origin and unit direction of the ray through a point in NDC.
+    fn ray(&self, ndc: Vec2) -> (Vec3, Vec3) {
+        let inverse = (self.projection_matrix() * self.view_matrix()).inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        (near, (far - near).normalize())
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+    }
+}
+
+/// How strongly the environment is mixed into the base color in reflective mode
+const REFLECTIVITY: f32 = 0.6;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    view_proj: [[f32; 4]; 4],
+    near: f32,
+    far: f32,
+    /// NDC offset already applied to `view_proj`, kept for a future TAA resolve
+    jitter: [f32; 2],
+    camera_position: [f32; 3],
+    /// 0 disables reflections, 1 is a perfect mirror
+    reflectivity: f32,
+    gamma: f32,
+    brightness: f32,
+    /// `OutputEncoding` for the target format
+    output_encoding: u32,
+    _padding: f32,
+    clip_plane: [f32; 4],
+    /// Alpha is unused
+    wireframe_front: [f32; 4],
+    wireframe_back: [f32; 4],
+    displacement_scale: f32,
+    /// Procedural material: checker cells per world unit, noise seed, and
+    /// whether it replaces vertex colors
+    material_scale: f32,
+    material_seed: u32,
+    material_enabled: u32,
+    /// Alpha is unused
+    material_colors: [[f32; 4]; 2],
+}
+
+impl Uniforms {
+    fn new(
+        camera: &Camera,
+        jitter: Vec2,
+        reflectivity: f32,
+        display: &DisplayAdjustment,
+        clip_plane: &ClipPlane,
+        wireframe: &WireframeColors,
+        displacement_scale: f32,
+    ) -> Self {
+        let projection = jitter::apply(camera.projection_matrix(), jitter);
+        Self {
+            view_proj: (projection * camera.view_matrix()).to_cols_array_2d(),
+            near: camera.near,
+            far: camera.far,
+            jitter: jitter.into(),
+            camera_position: camera.world_position().into(),
+            reflectivity,
+            gamma: display.gamma,
+            brightness: display.brightness,
+            output_encoding: OutputEncoding::None as u32,
+            _padding: 0.0,
+            clip_plane: clip_plane.equation().into(),
+            wireframe_front: Vec3::from(wireframe.front).extend(1.0).into(),
+            wireframe_back: Vec3::from(wireframe.back).extend(1.0).into(),
+            displacement_scale,
+            material_scale: 0.0,
+            material_seed: 0,
+            material_enabled: 0,
+            material_colors: [[0.0; 4]; 2],
+        }
+    }
+
+    fn with_output_encoding(self, encoding: OutputEncoding) -> Self {
+        Self { output_encoding: encoding as u32, ..self }
+    }
+
+    fn with_material(self, material: &ProceduralMaterial) -> Self {
+        Self {
+            material_scale: material.scale,
+            material_seed: material.seed,
+            material_enabled: material.enabled as u32,
+            material_colors: material.colors.map(|color| Vec3::from(color).extend(1.0).into()),
+            ..self
+        }
+    }
+}
+
+/// Axis-aligned inspection plane; geometry below `offset` along `axis` is cut away.
+#[derive(Debug, Default)]
+struct ClipPlane {
+    enabled: bool,
+    /// 0, 1 or 2 for X, Y or Z
+    axis: usize,
+    offset: f32,
+}
+
+impl ClipPlane {
+    /// Plane equation (normal, distance) for the shaders; all zeros, which clips nothing, when disabled.
+    fn equation(&self) -> glam::Vec4 {
+        if !self.enabled {
+            return glam::Vec4::ZERO;
+        }
+        let normal = Vec3::AXES[self.axis];
+        normal.extend(-self.offset)
+    }
+
+    /// Distances along the ray `origin + t * direction`, from `t = 0`, that
+    /// the plane keeps; `None` if it cuts the whole ray away.
+    fn ray_range(&self, origin: Vec3, direction: Vec3) -> Option<RangeInclusive<f32>> {
+        let plane = self.equation();
+        let (start, slope) = (plane.truncate().dot(origin) + plane.w, plane.truncate().dot(direction));
+        if slope == 0.0 {
+            return (start >= 0.0).then_some(0.0..=f32::INFINITY);
+        }
+        let crossing = -start / slope;
+        if slope > 0.0 {
+            Some(crossing.max(0.0)..=f32::INFINITY)
+        } else {
+            (crossing >= 0.0).then_some(0.0..=crossing)
+        }
+    }
+}
+
+/// Clip plane movement per key press, relative to the radius of the visible objects
+const CLIP_STEP: f32 = 0.05;
+
+/// Edge colors of front- and back-facing triangles in wireframe mode, so the
+/// near and far sides of a mesh can be told apart.
+#[derive(Debug)]
+struct WireframeColors {
+    front: [f32; 3],
+    back: [f32; 3],
+}
+
+impl Default for WireframeColors {
+    fn default() -> Self {
+        Self { front: [0.9, 0.9, 0.9], back: [0.8, 0.3, 0.2] }
+    }
+}
+
+/// Test-grid style material for judging scale, filtering and displacement
+/// without loading a texture: a world-space checkerboard of two colors with
+/// value noise on top. Replaces vertex colors in the flat and lit shaders.
+#[derive(Debug)]
+struct ProceduralMaterial {
+    enabled: bool,
+    /// Checker cells per world unit
+    scale: f32,
+    /// The same seed always gives the same noise
+    seed: u32,
+    colors: [[f32; 3]; 2],
+}
+
+impl Default for ProceduralMaterial {
+    fn default() -> Self {
+        Self { enabled: false, scale: 2.0, seed: 0, colors: [[0.85, 0.85, 0.85], [0.35, 0.35, 0.4]] }
+    }
+}
+
+/// Measuring mode: clicks pick points on surfaces instead of selecting
+/// objects, and the distance between each pair of points is reported.
+#[derive(Default)]
+struct Measurement {
+    enabled: bool,
+    /// World-space points picked so far, at most two
+    points: Vec<Vec3>,
+    /// Line between the two points once both are picked
+    line: Option<MeshBuffers>,
+}
+
+const MEASUREMENT_COLOR: [f32; 3] = [1.0, 1.0, 0.0];
+const FROZEN_FRUSTUM_COLOR: [f32; 3] = [0.2, 1.0, 0.4];
+
+/// Final color adjustment for display calibration: `brightness` is added,
+/// then the result is raised to `1 / gamma`. The defaults leave colors unchanged.
+#[derive(Debug)]
+struct DisplayAdjustment {
+    gamma: f32,
+    brightness: f32,
+}
+
+impl Default for DisplayAdjustment {
+    fn default() -> Self {
+        Self { gamma: 1.0, brightness: 0.0 }
+    }
+}
+
+/// Displacement scale used when a height map is given, and its step per key press
+const DEFAULT_DISPLACEMENT_SCALE: f32 = 0.1;
+const DISPLACEMENT_STEP: f32 = 0.02;
+
+/// Motion blur strength without `--motion-blur`
+const DEFAULT_MOTION_BLUR: f32 = 0.8;
+
+/// Focal distance factor and aperture step (in pixels) per key press
+const FOCUS_STEP: f32 = 1.1;
+const APERTURE_STEP: f32 = 1.0;
+const MAX_APERTURE: f32 = 24.0;
+
+const GAMMA_STEP: f32 = 0.1;
+const BRIGHTNESS_STEP: f32 = 0.05;
+
+/// Depth, stencil and color write state that differs between scene pipelines.
+struct PipelineState {
+    vertex_entry_point: &'static str,
+    topology: wgpu::PrimitiveTopology,
+    polygon_mode: wgpu::PolygonMode,
+    cull_mode: Option<wgpu::Face>,
+    depth_compare: wgpu::CompareFunction,
+    depth_write: bool,
+    stencil: wgpu::StencilState,
+    color_writes: wgpu::ColorWrites,
+    /// Drawn straight into the final frame, after post-processing: single
+    /// sampled and without a depth buffer
+    overlay: bool,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self {
+            vertex_entry_point: "vs_main",
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: Some(wgpu::Face::Back),
+            depth_compare: wgpu::CompareFunction::Less,
+            depth_write: true,
+            stencil: wgpu::StencilState::default(),
+            color_writes: wgpu::ColorWrites::ALL,
+            overlay: false,
+        }
+    }
+}
+
+/// Two-pass stencil outline: `mark` writes the selected object's silhouette
+/// into the stencil buffer, `outline` draws a slightly enlarged copy only
+/// where the stencil is unmarked.
+struct OutlinePipelines {
+    mark: wgpu::RenderPipeline,
+    outline: wgpu::RenderPipeline,
+}
+
+/// Enlargement of the outline copy, relative to the object's bounding radius
+const OUTLINE_WIDTH: f32 = 0.04;
+
+/// Timestamps of the startup phases, from `init_graphics()` entry to the first presented frame.
+struct StartupTimer {
+    start: Instant,
+    adapter: Instant,
+    device: Instant,
+    pipeline: Instant,
+}
+
+impl StartupTimer {
+    fn finish(&self) {
+        let ms = |from: Instant, to: Instant| (to - from).as_secs_f64() * 1000.0;
+        let now = Instant::now();
+        println!(
+            "Startup: {:.1} ms total (adapter {:.1} ms, device {:.1} ms, pipeline {:.1} ms, first frame {:.1} ms)",
+            ms(self.start, now),
+            ms(self.start, self.adapter),
+            ms(self.adapter, self.device),
+            ms(self.device, self.pipeline),
+            ms(self.pipeline, now),
+        );
+    }
+}
+
+/// Per-frame vertex/fragment invocation counters, read back without stalling
+/// the frame: a frame's counters are printed once their copy has been mapped,
+/// and frames rendered meanwhile aren't resolved.
+struct PipelineStats {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Set once the copy being read back has finished mapping
+    in_flight: Option<Arc<AtomicBool>>,
+    map_requested: bool,
+}
+
+impl PipelineStats {
+    // One u64 per enabled statistic, in bit order of `PipelineStatisticsTypes`
+    const SIZE: wgpu::BufferAddress = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+    fn new(device: &Device) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Pipeline Statistics Query Set"),
+            ty: wgpu::QueryType::PipelineStatistics(
+                wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+                    | wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS,
+            ),
+            count: 1,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pipeline Statistics Resolve Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pipeline Statistics Readback Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self { query_set, resolve_buffer, readback_buffer, in_flight: None, map_requested: false }
+    }
+
+    /// Copies this frame's counters towards the CPU, unless an earlier
+    /// frame's copy hasn't been read yet.
+    fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.in_flight.is_some() {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..1, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, Self::SIZE);
+        self.in_flight = Some(Arc::new(AtomicBool::new(false)));
+    }
+
+    /// Starts mapping the counters resolved this frame; call after submitting.
+    fn map_results(&mut self) {
+        let Some(mapped) = &self.in_flight else { return };
+        if std::mem::replace(&mut self.map_requested, true) {
+            return;
+        }
+        let mapped = mapped.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                mapped.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    /// Prints the counters if their copy has finished mapping; doesn't wait.
+    fn print(&mut self, device: &Device) {
+        device.poll(wgpu::Maintain::Poll);
+        let Some(mapped) = &self.in_flight else { return };
+        if !mapped.load(Ordering::Acquire) {
+            return;
+        }
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let counts: &[u64] = bytemuck::cast_slice(&data);
+            println!("Pipeline statistics: {} vertex invocations, {} fragment invocations", counts[0], counts[1]);
+        }
+        self.readback_buffer.unmap();
+        self.in_flight = None;
+        self.map_requested = false;
+    }
+}
+
+
+
+impl Renderer {
+    /// A renderer for `settings`; `init_graphics` sets up the GPU side.
+    pub fn new(settings: Config) -> Self {
+        let mut renderer = Self { settings, ..Default::default() };
+        if let Some(color) = renderer.settings.wireframe_front {
+            renderer.wireframe_colors.front = color;
+        }
+        if let Some(color) = renderer.settings.wireframe_back {
+            renderer.wireframe_colors.back = color;
+        }
+        renderer.material.enabled = renderer.settings.procedural;
+        if let Some(scale) = renderer.settings.procedural_scale {
+            renderer.material.scale = scale;
+        }
+        if let Some(seed) = renderer.settings.procedural_seed {
+            renderer.material.seed = seed;
+        }
+        if let Some(colors) = renderer.settings.procedural_colors {
+            renderer.material.colors = colors;
+        }
+        if let Some((acceleration, friction)) = renderer.settings.inertia {
+            renderer.dolly = Inertia::new(acceleration, friction);
+        }
+        if let Some(scale) = renderer.settings.time_scale {
+            renderer.animation_clock.scale = scale;
+        }
+        if let Some(rate) = renderer.settings.spin {
+            let rate = rate.to_radians();
+            // Like the showcase orbit, turns the world about its up axis
+            renderer.on_tick(move |camera, dt| camera.rotation *= Quat::from_rotation_y(rate * dt));
+        }
+        renderer
+    }
+
+    pub fn settings(&self) -> &Config {
+        &self.settings
+    }
+
+    /// The device, once `init_graphics` has succeeded, for creating the
+    /// textures to `render_into`.
+    pub fn device(&self) -> Option<&Device> {
+        self.device.as_ref()
+    }
+
+    pub fn queue(&self) -> Option<&Queue> {
+        self.queue.as_ref()
+    }
+
+    /// Format and size views given to `render_into` must have.
+    pub fn target_config(&self) -> Option<&SurfaceConfiguration> {
+        self.config.as_ref()
+    }
+
+    /// Index of the current frame, counting from 0, which input logs are
+    /// keyed on. It moves on in `begin_frame` once the previous frame is drawn.
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    /// Last cursor position given to `apply_input`, in window pixels.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// Reconfigures the surface for a new size with the currently preferred
+    /// format, rebuilding the pipelines if the format changed. Without a
+    /// surface, resizes the offscreen targets.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if let (Some(device), Some(adapter), Some(config)) = (&self.device, &self.adapter, &mut self.config) {
+            config.width = width;
+            config.height = height;
+            if let Some(surface) = &self.surface {
+                // The preferred format can change, e.g. after moving to another monitor
+                (config.format, self.output_encoding) =
+                    color_space::choose(&surface.get_capabilities(adapter).formats, self.settings.color_space);
+                surface.configure(device, config);
+            }
+            self.camera.aspect = width as f32 / height as f32;
+        }
+        self.create_render_targets();
+        if let (Some(config), Some(built_for)) = (&self.config, self.pipeline_format)
+            && built_for != config.format
+        {
+            println!("Surface format changed from {built_for:?} to {:?}; rebuilding pipelines", config.format);
+            self.pipelines = self.create_pipelines(config.format, Some(wgpu::Face::Back));
+            self.double_sided_pipelines = self.create_pipelines(config.format, None);
+            self.outline_pipelines = self.create_outline_pipelines(config.format);
+            self.bounds_pipeline = Some(self.create_bounds_pipeline(config.format));
+            self.line_pipeline = Some(self.create_line_pipeline(config.format, false));
+            self.depth_tested_line_pipeline = Some(self.create_line_pipeline(config.format, true));
+            self.screen_line_pipeline = Some(self.create_screen_line_pipeline(config.format));
+            self.wireframe_pipeline = self.create_wireframe_pipeline(config.format);
+            self.triangle_pipeline = self.create_triangle_pipeline(config.format);
+            self.hud = Some(Hud::new(self.device.as_ref().unwrap(), config.format));
+            self.pipeline_format = Some(config.format);
+        }
+    }
+
+    /// (Re)creates the depth buffer, MSAA color target and post-processing
+    /// targets at the surface size.
+    fn create_render_targets(&mut self) {
+        let (Some(device), Some(config)) = (&self.device, &self.config) else { return };
+        let samples = self.sample_count();
+        let depth_format = self.settings.depth_format.unwrap();
+        // Depth of field reads the depth buffer in the post pass
+        let depth_texture = create_attachment(
+            device,
+            "Depth Texture",
+            depth_format,
+            (config.width, config.height),
+            samples,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        self.depth_view = Some(depth_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        self.msaa_view = (samples > 1).then(|| {
+            create_attachment(device, "MSAA Color Texture", config.format, (config.width, config.height), samples, wgpu::TextureUsages::empty())
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        self.post = (self.motion_blur_enabled || self.depth_of_field.enabled)
+            .then(|| PostProcess::new(device, config.format, config.width, config.height, &depth_texture, samples));
+        self.overdraw = self.settings.overdraw.then(|| {
+            let layouts = [self.create_bind_group_layout(), self.create_object_bind_group_layout()];
+            Overdraw::new(device, config.format, (config.width, config.height), &[&layouts[0], &layouts[1]], &Vertex::layout())
+        });
+        self.depth_texture = Some(depth_texture);
+    }
+
+    /// Samples per pixel of the render targets and pipelines.
+    fn sample_count(&self) -> u32 {
+        self.settings.msaa.unwrap_or(1)
+    }
+
+    /// Applies window input: drags look around, clicks pick or measure,
+    /// keys toggle settings and the wheel dollies.
+    pub fn apply_input(&mut self, event: InputEvent) {
+        if let Some(showcase) = &mut self.showcase
+            && showcase.input(self.frame_clock.unwrap_or_else(Instant::now))
+        {
+            println!("Showcase orbit stopped");
+        }
+        match event {
+            InputEvent::MouseButton { pressed } => {
+                self.mouse_pressed = pressed;
+                self.trackball_drag = None;
+                if pressed
+                    && self.settings.camera_mode == CameraMode::Trackball
+                    && let Some(cursor) = self.cursor_position
+                {
+                    self.trackball_drag =
+                        self.trackball_point(cursor).map(|point| (TrackballDrag::new(point, self.camera.rotation), cursor));
+                }
+                if self.mouse_pressed {
+                    self.drag_distance = Some(0.0);
+                } else if let (Some(dragged), Some(cursor)) = (self.drag_distance.take(), self.cursor_position)
+                    && dragged < CLICK_TOLERANCE
+                {
+                    // The cursor is held during drags, so it is still where the button went down
+                    if self.measurement.enabled {
+                        self.measure(cursor);
+                    } else {
+                        self.pick(cursor);
+                    }
+                }
+            }
+            InputEvent::CursorMoved(position) => self.cursor_position = Some(position),
+            InputEvent::Key(key) => self.handle_key(key),
+            InputEvent::Scroll(_) if self.zoom_locked => (),
+            InputEvent::Scroll(lines) => {
+                // Move camera forward/backward based on scroll
+                let step = Vec3::new(0.0, 0.0, -lines * self.zoom_speed());
+                self.camera.position += self.dolly.push(step);
+            }
+            InputEvent::MouseMotion(delta) => {
+                // Only rotate camera when left mouse button is held
+                if let Some(dragged) = &mut self.drag_distance {
+                    *dragged += delta.length();
+                }
+                if let Some((drag, cursor)) = &mut self.trackball_drag {
+                    *cursor += delta;
+                    let (drag, cursor) = (*drag, *cursor);
+                    if let Some(point) = self.trackball_point(cursor) {
+                        self.camera.rotation = drag.rotation(point);
+                    }
+                } else if self.mouse_pressed {
+                    let sensitivity = 0.01;
+                    let delta_x = delta.x * sensitivity;
+                    let delta_y = delta.y * sensitivity * if self.settings.invert_y { -1.0 } else { 1.0 };
+
+                    let rot_y = Quat::from_axis_angle(Vec3::Y, delta_x);
+                    let rot_x = Quat::from_axis_angle(Vec3::X, delta_y);
+                    self.camera.rotation = rot_y * rot_x * self.camera.rotation;
+                }
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Tab if !self.pipelines.is_empty() => {
+                self.active_shader = (self.active_shader + 1) % self.pipelines.len();
+                println!("Shader: {}", SHADER_VARIANTS[self.active_shader].name);
+            }
+            KeyCode::KeyJ => {
+                self.settings.jitter = !self.settings.jitter;
+                println!("Projection jitter: {}", if self.settings.jitter { "on" } else { "off" });
+            }
+            KeyCode::KeyS => {
+                self.settings.smart_speed = !self.settings.smart_speed;
+                println!("Smart zoom speed: {}", if self.settings.smart_speed { "on" } else { "off" });
+            }
+            KeyCode::KeyV => {
+                self.settings.camera_mode = match self.settings.camera_mode {
+                    CameraMode::Look => CameraMode::Trackball,
+                    CameraMode::Trackball => CameraMode::Look,
+                };
+                println!("Camera mode: {:?}", self.settings.camera_mode);
+            }
+            KeyCode::KeyQ | KeyCode::KeyE => {
+                self.animation_clock.step(key == KeyCode::KeyE);
+                println!("Animation speed: {}x", self.animation_clock.scale);
+            }
+            KeyCode::KeyU => {
+                // Keep the eye where it is, so the view only turns about its direction
+                let eye = self.camera.world_position();
+                self.camera.rotation = level_roll(self.camera.rotation);
+                self.camera.position = self.camera.rotation * eye;
+                println!("Camera roll leveled");
+            }
+            KeyCode::KeyX => {
+                self.show_crosshair = !self.show_crosshair;
+                println!("Crosshair: {}", if self.show_crosshair { "on" } else { "off" });
+            }
+            KeyCode::KeyZ => {
+                self.zoom_locked = !self.zoom_locked;
+                self.dolly.stop();
+                println!("Scroll zoom: {}", if self.zoom_locked { "locked, scrolling is ignored" } else { "unlocked" });
+            }
+            KeyCode::KeyY => {
+                self.settings.invert_y = !self.settings.invert_y;
+                println!("Inverted vertical look: {}", if self.settings.invert_y { "on" } else { "off" });
+            }
+            KeyCode::KeyC if !self.objects.is_empty() => {
+                self.carousel = !self.carousel;
+                self.arrange_objects();
+                if self.carousel {
+                    println!("Carousel: {}", self.objects[self.active_object].name);
+                } else {
+                    println!("Showing all {} objects", self.objects.len());
+                }
+            }
+            KeyCode::ArrowLeft | KeyCode::ArrowRight if self.carousel => {
+                let count = self.objects.len();
+                self.active_object = if key == KeyCode::ArrowRight {
+                    (self.active_object + 1) % count
+                } else {
+                    (self.active_object + count - 1) % count
+                };
+                self.arrange_objects();
+                println!("Carousel: {}", self.objects[self.active_object].name);
+            }
+            KeyCode::KeyL if self.lights.len() < MAX_LIGHTS => {
+                let position = self.camera.world_position();
+                self.lights.push(PointLight::new(position, self.lights.len()));
+                println!("Added point light {} at {position}", self.lights.len());
+            }
+            KeyCode::KeyL => println!("Already at the maximum of {MAX_LIGHTS} point lights"),
+            KeyCode::KeyK if self.lights.pop().is_some() => {
+                println!("Removed point light; {} left", self.lights.len());
+            }
+            KeyCode::KeyO => {
+                self.settings.occlusion_culling = !self.settings.occlusion_culling;
+                if let Some(occlusion) = &mut self.occlusion {
+                    occlusion.reset();
+                }
+                println!("Occlusion culling: {}", if self.settings.occlusion_culling { "on" } else { "off" });
+            }
+            KeyCode::KeyP => {
+                self.clip_plane.enabled = !self.clip_plane.enabled;
+                println!("Clip plane: {}", if self.clip_plane.enabled { "on" } else { "off" });
+            }
+            KeyCode::KeyI => {
+                self.clip_plane.axis = (self.clip_plane.axis + 1) % 3;
+                self.clip_plane.offset = self.visible_bounds().map_or(0.0, |bounds| bounds.center()[self.clip_plane.axis]);
+                println!("Clip plane axis: {}", ["X", "Y", "Z"][self.clip_plane.axis]);
+            }
+            KeyCode::PageUp | KeyCode::PageDown if self.clip_plane.enabled => {
+                let step = self.visible_bounds().map_or(1.0, |bounds| bounds.radius()) * CLIP_STEP;
+                self.clip_plane.offset += if key == KeyCode::PageUp { step } else { -step };
+                println!("Clip plane at {} = {:.3}", ["x", "y", "z"][self.clip_plane.axis], self.clip_plane.offset);
+            }
+            KeyCode::KeyW if self.wireframe_pipeline.is_some() => {
+                self.wireframe = !self.wireframe;
+                println!("Wireframe: {}", if self.wireframe { "on" } else { "off" });
+            }
+            KeyCode::KeyW => println!("Wireframe mode needs POLYGON_MODE_LINE, which this adapter does not support"),
+            KeyCode::KeyT if self.triangle_pipeline.is_some() => {
+                self.triangle_colors = !self.triangle_colors;
+                println!("Triangle colors: {}", if self.triangle_colors { "on" } else { "off" });
+            }
+            KeyCode::KeyT => println!("Triangle colors need SHADER_PRIMITIVE_INDEX, which this adapter does not support"),
+            KeyCode::KeyF => {
+                if self.frozen_frustum.take().is_some() {
+                    println!("Culling frustum follows the camera again");
+                } else {
+                    let corners = frustum::corners(self.cull_view_proj);
+                    let outline = Mesh::lines(frustum::edges().map(|(a, b)| (corners[a], corners[b])), FROZEN_FRUSTUM_COLOR);
+                    self.frozen_frustum = Some(MeshBuffers::new(self.device.as_ref().unwrap(), "Frozen Frustum", &outline));
+                    println!("Culling frustum frozen; move the camera to watch objects outside it disappear");
+                }
+            }
+            KeyCode::KeyM => {
+                self.measurement = Measurement { enabled: !self.measurement.enabled, ..Default::default() };
+                println!("Measuring: {}", if self.measurement.enabled { "on, click two points" } else { "off" });
+            }
+            KeyCode::KeyB => {
+                self.motion_blur_enabled = !self.motion_blur_enabled;
+                self.create_render_targets();
+                println!("Motion blur: {}", if self.motion_blur_enabled { "on" } else { "off" });
+            }
+            KeyCode::KeyD => {
+                self.depth_of_field.enabled = !self.depth_of_field.enabled;
+                self.create_render_targets();
+                println!("Depth of field: {}", if self.depth_of_field.enabled { "on" } else { "off" });
+            }
+            KeyCode::KeyG if self.depth_of_field.enabled => {
+                if let Some(cursor) = self.cursor_position {
+                    self.focus_on(cursor);
+                }
+            }
+            KeyCode::Home | KeyCode::End if self.depth_of_field.enabled => {
+                let factor = if key == KeyCode::End { FOCUS_STEP } else { 1.0 / FOCUS_STEP };
+                self.depth_of_field.focal_distance = (self.depth_of_field.focal_distance * factor).clamp(self.camera.near, self.camera.far);
+                println!("Focal distance: {:.3}", self.depth_of_field.focal_distance);
+            }
+            KeyCode::Semicolon | KeyCode::Quote if self.depth_of_field.enabled => {
+                let step = if key == KeyCode::Quote { APERTURE_STEP } else { -APERTURE_STEP };
+                self.depth_of_field.aperture = (self.depth_of_field.aperture + step).clamp(0.0, MAX_APERTURE);
+                println!("Aperture: {:.0} px", self.depth_of_field.aperture);
+            }
+            KeyCode::KeyH => {
+                self.show_hud = !self.show_hud;
+                println!("HUD: {}", if self.show_hud { "on" } else { "off" });
+            }
+            KeyCode::KeyN => {
+                self.material.enabled = !self.material.enabled;
+                println!("Procedural material: {}", if self.material.enabled { "on" } else { "off" });
+            }
+            KeyCode::KeyR => {
+                self.reflective = !self.reflective;
+                println!("Reflective shading: {}", if self.reflective { "on" } else { "off" });
+            }
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                let step = if key == KeyCode::BracketRight { GAMMA_STEP } else { -GAMMA_STEP };
+                self.display.gamma = (self.display.gamma + step).clamp(0.2, 5.0);
+                println!("Gamma: {:.1}", self.display.gamma);
+            }
+            KeyCode::Minus | KeyCode::Equal => {
+                let step = if key == KeyCode::Equal { BRIGHTNESS_STEP } else { -BRIGHTNESS_STEP };
+                self.display.brightness = (self.display.brightness + step).clamp(-1.0, 1.0);
+                println!("Brightness: {:+.2}", self.display.brightness);
+            }
+            KeyCode::Comma | KeyCode::Period => {
+                let step = if key == KeyCode::Period { DISPLACEMENT_STEP } else { -DISPLACEMENT_STEP };
+                self.displacement_scale = (self.displacement_scale + step).clamp(-1.0, 1.0);
+                println!("Displacement scale: {:.2}", self.displacement_scale);
+            }
+            KeyCode::F3 => {
+                self.settings.overdraw = !self.settings.overdraw;
+                self.create_render_targets();
+                println!("Overdraw view: {}", if self.settings.overdraw { "on" } else { "off" });
+            }
+            KeyCode::F5 => self.reload_shaders(),
+            KeyCode::F12 => {
+                let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+                self.save_panorama(&PathBuf::from(format!("panorama-{seconds}.png")));
+            }
+            KeyCode::Digit0 => {
+                self.display = DisplayAdjustment::default();
+                println!("Gamma and brightness reset");
+            }
+            _ => (),
+        }
+    }
+
+    /// Sets up the device, pipelines and scene, presenting to the window if
+    /// there is one and rendering offscreen at `OFFSCREEN_SIZE` otherwise.
+    /// `target` is the window to present to and its size in pixels. Fails if
+    /// no adapter or device can be had.
+    pub fn init_graphics(&mut self, target: Option<(wgpu::SurfaceTarget<'static>, (u32, u32))>) -> Result<(), String> {
+        let start = Instant::now();
+        let (target, size) = target.unzip();
+        // A new window starts with the button up
+        self.mouse_pressed = false;
+
+        // Create instance
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: if self.settings.safe_mode { wgpu::Backends::GL } else { wgpu::Backends::all() },
+            dx12_shader_compiler: Default::default(),
+            flags: wgpu::InstanceFlags::default(),
+            gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
+        });
+
+        // Create surface
+        let surface = target
+            .map(|target| instance.create_surface(target))
+            .transpose()
+            .map_err(|e| format!("Failed to create a surface: {e}"))?;
+
+        // Get adapter
+        let adapter = request_adapter(&instance, surface.as_ref(), self.settings.adapter, self.settings.safe_mode)
+            .ok_or("No GPU adapter found, not even a software fallback")?;
+        let adapter_ready = Instant::now();
+
+        let required_features = self.settings.negotiate_features(adapter.features());
+        // Safe mode asks for no more than WebGL2-class hardware offers, except
+        // that render targets may still be as large as the adapter allows
+        let required_limits = if self.settings.safe_mode {
+            wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+        } else {
+            wgpu::Limits::default()
+        };
+
+        // Create device and queue
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features,
+                required_limits,
+            },
+            None,
+        ))
+        .map_err(|e| format!("Failed to create a device: {e}"))?;
+        let device_ready = Instant::now();
+        self.settings.depth_format = Some(supported_depth_format(&adapter, self.settings.depth_format));
+
+        if self.settings.pipeline_stats {
+            self.pipeline_stats = Some(PipelineStats::new(&device));
+        }
+
+        self.device = Some(device);
+        self.queue = Some(queue);
+        self.instance = Some(instance);
+
+        // Configure surface; without one the configuration still describes the render targets
+        let config = match (&surface, size) {
+            (Some(surface), Some((width, height))) => {
+                let surface_caps = surface.get_capabilities(&adapter);
+                self.settings.alpha_mode = Some(supported_alpha_mode(&surface_caps.alpha_modes, self.settings.alpha_mode));
+                let format;
+                (format, self.output_encoding) = color_space::choose(&surface_caps.formats, self.settings.color_space);
+                let config = wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format,
+                    width,
+                    height,
+                    present_mode: wgpu::PresentMode::Fifo,
+                    alpha_mode: self.settings.alpha_mode.unwrap(),
+                    view_formats: vec![],
+                    desired_maximum_frame_latency: 2,
+                };
+                surface.configure(self.device.as_ref().unwrap(), &config);
+                config
+            }
+            _ => {
+                let format;
+                (format, self.output_encoding) = color_space::choose(&OFFSCREEN_FORMATS, self.settings.color_space);
+                wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format,
+                    width: OFFSCREEN_SIZE.0,
+                    height: OFFSCREEN_SIZE.1,
+                    present_mode: wgpu::PresentMode::Fifo,
+                    alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                    view_formats: vec![],
+                    desired_maximum_frame_latency: 2,
+                }
+            }
+        };
+        let surface_format = config.format;
+        println!("Color space {:?}: rendering to {surface_format:?}, shader encoding {:?}", self.settings.color_space, self.output_encoding);
+        self.camera.aspect = config.width as f32 / config.height as f32;
+        self.settings.msaa = Some(supported_sample_count(&adapter, &[surface_format, self.settings.depth_format.unwrap()], self.settings.msaa));
+        self.config = Some(config);
+        self.surface = surface;
+        self.motion_blur = self.settings.motion_blur.unwrap_or(DEFAULT_MOTION_BLUR);
+        self.motion_blur_enabled = self.settings.motion_blur.is_some();
+        self.depth_of_field.enabled = self.settings.depth_of_field;
+        self.create_render_targets();
+
+        self.adapter = Some(adapter);
+
+        self.pipelines = self.create_pipelines(surface_format, Some(wgpu::Face::Back));
+        self.double_sided_pipelines = self.create_pipelines(surface_format, None);
+        self.outline_pipelines = self.create_outline_pipelines(surface_format);
+        self.bounds_pipeline = Some(self.create_bounds_pipeline(surface_format));
+        self.line_pipeline = Some(self.create_line_pipeline(surface_format, false));
+        self.depth_tested_line_pipeline = Some(self.create_line_pipeline(surface_format, true));
+        self.screen_line_pipeline = Some(self.create_screen_line_pipeline(surface_format));
+        self.hud = Some(Hud::new(self.device.as_ref().unwrap(), surface_format));
+        self.show_hud = self.settings.hud;
+        self.wireframe_pipeline = self.create_wireframe_pipeline(surface_format);
+        self.triangle_pipeline = self.create_triangle_pipeline(surface_format);
+        self.pipeline_format = Some(surface_format);
+        self.startup = Some(StartupTimer {
+            start,
+            adapter: adapter_ready,
+            device: device_ready,
+            pipeline: Instant::now(),
+        });
+
+        // Load the models given on the command line, or fall back to the --start scene
+        let device = self.device.as_ref().unwrap();
+        for path in &self.settings.models {
+            match Mesh::load(path) {
+                Ok(mut mesh) => {
+                    if self.settings.fix_winding {
+                        let flipped = mesh.fix_winding();
+                        if flipped > 0 {
+                            println!("{}: flipped {flipped} triangles to match their normals", path.display());
+                        }
+                    }
+                    self.objects.push(RenderObject::new(device, path.display().to_string(), &mesh));
+                }
+                Err(err) => eprintln!("Failed to load model {err}"),
+            }
+        }
+        if self.settings.lod_demo {
+            for i in 0..LOD_DEMO_SPHERES {
+                let mut sphere = RenderObject::new(device, format!("sphere {i}"), &Mesh::sphere(64));
+                for (distance, segments) in LOD_DEMO_LEVELS {
+                    sphere.add_lod(device, distance, &Mesh::sphere(segments));
+                }
+                self.objects.push(sphere);
+            }
+        }
+        if self.objects.is_empty() {
+            match self.settings.start {
+                StartScene::Sample => match Mesh::sample() {
+                    Ok(mesh) => self.objects.push(RenderObject::new(device, "torus knot", &mesh)),
+                    Err(e) => {
+                        eprintln!("Failed to parse the embedded sample model ({e}); showing the cube instead");
+                        self.objects.push(RenderObject::new(device, "cube", &Mesh::cube()));
+                    }
+                },
+                StartScene::Cube => self.objects.push(RenderObject::new(device, "cube", &Mesh::cube())),
+                StartScene::Triangle => self.objects.push(RenderObject::new(device, "triangle", &Mesh::triangle())),
+                StartScene::Empty => println!("Starting with an empty scene"),
+            }
+        }
+        for &index in &self.settings.double_sided {
+            match self.objects.get_mut(index) {
+                Some(object) => object.double_sided = true,
+                None => eprintln!("Ignoring --double-sided {index}: there are only {} objects", self.objects.len()),
+            }
+        }
+        for &(index, model) in &self.settings.shading {
+            match self.objects.get_mut(index) {
+                Some(object) => object.shading = Some(model),
+                None => eprintln!("Ignoring --shading {index}:{}: there are only {} objects", model.name(), self.objects.len()),
+            }
+        }
+        for &(index, [r, g, b]) in &self.settings.tints {
+            match self.objects.get_mut(index) {
+                Some(object) => object.tint = Vec4::new(r, g, b, 1.0),
+                None => eprintln!("Ignoring --tint {index}: there are only {} objects", self.objects.len()),
+            }
+        }
+        for object in &self.objects {
+            match object.shading {
+                Some(model) => println!("{}: {} shading", object.name, model.name()),
+                None => println!("{}: active gallery shader", object.name),
+            }
+        }
+        for &(child, parent) in &self.settings.parents {
+            if let Err(err) = scene::set_parent(&mut self.objects, child, Some(parent)) {
+                eprintln!("Ignoring --parent {child}:{parent}: {err}");
+            }
+        }
+        // Extra slots for the enlarged copy of the selected object drawn by
+        // the outline pass, for each object's occlusion bounds, for the
+        // measurement line and for the crosshair
+        self.object_buffer = Some(ObjectBuffer::new(device, &self.create_object_bind_group_layout(), self.objects.len() * 2 + 3));
+        let arm = self.settings.crosshair_size.unwrap_or(DEFAULT_CROSSHAIR_SIZE);
+        let crosshair = Mesh::lines(
+            [(Vec3::new(-arm, 0.0, 0.0), Vec3::new(arm, 0.0, 0.0)), (Vec3::new(0.0, -arm, 0.0), Vec3::new(0.0, arm, 0.0))],
+            self.settings.crosshair_color.unwrap_or(DEFAULT_CROSSHAIR_COLOR),
+        );
+        self.crosshair = Some(MeshBuffers::new(device, "Crosshair", &crosshair));
+        self.show_crosshair = self.settings.crosshair;
+        self.occlusion = Some(OcclusionCulling::new(device, self.objects.len()));
+
+        // Create uniform buffer and bind group; every frame writes its uniforms before drawing
+        let slots = self.settings.uniform_ring.unwrap_or(uniform_ring::DEFAULT_SLOTS);
+        self.uniform_ring = Some(UniformRing::new(device, "Uniform Buffer", std::mem::size_of::<Uniforms>(), slots));
+        let light_buffer = self.device.as_ref().unwrap().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&LightUniforms::new(&self.lights)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let device = self.device.as_ref().unwrap();
+        let queue = self.queue.as_ref().unwrap();
+        self.environment = Some(Environment::new(device, queue));
+        self.light_buffer = Some(light_buffer);
+        self.texture_sampler = Some(Texture::create_sampler(device, self.settings.anisotropy.unwrap_or(1)));
+        let initial = match &self.settings.texture {
+            Some(path) => {
+                // Decoding can take a while for large images; show a placeholder meanwhile
+                self.texture_loader = Some(TextureLoader::spawn(path.clone()));
+                self.active_shader = SHADER_VARIANTS.iter().position(|v| v.name == "textured").unwrap();
+                Image::placeholder()
+            }
+            None => Image::white(),
+        };
+        self.base_texture = Some(Texture::new(device, queue, "Base Texture", &initial));
+        let height_map = match &self.settings.displacement {
+            Some(path) => match Image::open(path) {
+                Ok(image) => {
+                    self.displacement_scale = DEFAULT_DISPLACEMENT_SCALE;
+                    println!("Loaded height map {} ({}x{})", path.display(), image.width, image.height);
+                    self.fit_to_device(image, "Height map")
+                }
+                Err(e) => {
+                    eprintln!("Failed to load height map {}: {e}", path.display());
+                    Image::black()
+                }
+            },
+            None => Image::black(),
+        };
+        self.displacement_texture = Some(Texture::linear(device, queue, "Displacement Texture", &height_map));
+        self.uniform_bind_group = Some(self.create_uniform_bind_group());
+        Ok(())
+    }
+
+    /// Binds the uniforms, lights and textures; rebuilt whenever a texture is replaced.
+    fn create_uniform_bind_group(&self) -> wgpu::BindGroup {
+        let environment = self.environment.as_ref().unwrap();
+        self.device.as_ref().unwrap().create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.create_bind_group_layout(),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_ring.as_ref().unwrap().binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&environment.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&environment.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.light_buffer.as_ref().unwrap().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&self.base_texture.as_ref().unwrap().view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(self.texture_sampler.as_ref().unwrap()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&self.displacement_texture.as_ref().unwrap().view),
+                },
+            ],
+            label: Some("uniform_bind_group"),
+        })
+    }
+
+    /// Downscales `image` if it's larger than the device's 2D texture size limit.
+    fn fit_to_device(&self, image: Image, what: &str) -> Image {
+        let max_size = self.device.as_ref().unwrap().limits().max_texture_dimension_2d;
+        if image.width.max(image.height) <= max_size {
+            return image;
+        }
+        let image = image.downscaled(max_size);
+        println!("{what} exceeds the device's {max_size}px size limit; downscaled to {}x{}", image.width, image.height);
+        image
+    }
+
+    /// Swaps in the background-loaded texture once it has been decoded.
+    fn poll_texture_loader(&mut self) {
+        let Some(result) = self.texture_loader.as_ref().and_then(TextureLoader::poll) else { return };
+        let path = self.texture_loader.take().unwrap().path;
+        match result {
+            Ok(image) => {
+                println!("Loaded texture {} ({}x{})", path.display(), image.width, image.height);
+                let image = self.fit_to_device(image, "Texture");
+                let texture = Texture::new(self.device.as_ref().unwrap(), self.queue.as_ref().unwrap(), "Base Texture", &image);
+                self.base_texture = Some(texture);
+                self.uniform_bind_group = Some(self.create_uniform_bind_group());
+            }
+            Err(e) => eprintln!("Failed to load texture {}: {e}", path.display()),
+        }
+    }
+
+    /// Creates the shaders and a render pipeline for each, in `SHADER_VARIANTS` order.
+    fn create_pipelines(&self, format: wgpu::TextureFormat, cull_mode: Option<wgpu::Face>) -> Vec<wgpu::RenderPipeline> {
+        let vertex_layout = Vertex::layout();
+        SHADER_VARIANTS
+            .iter()
+            .map(|variant| self.create_variant_pipeline(variant, &variant.source(), &vertex_layout, format, cull_mode))
+            .collect()
+    }
+
+    fn create_variant_pipeline(
+        &self,
+        variant: &ShaderVariant,
+        source: &str,
+        vertex_layout: &VertexLayout,
+        format: wgpu::TextureFormat,
+        cull_mode: Option<wgpu::Face>,
+    ) -> wgpu::RenderPipeline {
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(variant.name),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let state = PipelineState { cull_mode, ..Default::default() };
+        self.create_render_pipeline(&shader, variant.entry_point, vertex_layout, format, &state)
+    }
+
+    /// Renders a 360° panorama around the camera and writes it to `path` as a PNG.
+    pub fn save_panorama(&mut self, path: &Path) {
+        let start = Instant::now();
+        match self.capture_panorama().and_then(|image| std::fs::write(path, image.to_png()).map_err(|e| e.to_string())) {
+            Ok(()) => println!("Saved panorama to {} in {:.0?}", path.display(), start.elapsed()),
+            Err(e) => eprintln!("Failed to save panorama to {}: {e}", path.display()),
+        }
+    }
+
+    /// Renders the cube faces around the camera's eye at `panorama::FACE_SIZE`
+    /// and reprojects them. Effects that span frames or depend on the screen
+    /// (motion blur, depth of field, jitter, occlusion culling, the HUD and
+    /// crosshair) are off for the faces, since they would differ between them
+    /// and show seams.
+    fn capture_panorama(&mut self) -> Result<Image, String> {
+        let (Some(device), Some(config)) = (&self.device, &mut self.config) else {
+            return Err("graphics are not initialized".to_string());
+        };
+        let cube = panorama::create_cube(device, config.format, panorama::FACE_SIZE);
+        let size = (config.width, config.height);
+        (config.width, config.height) = (panorama::FACE_SIZE, panorama::FACE_SIZE);
+        let effects = (
+            std::mem::take(&mut self.motion_blur_enabled),
+            std::mem::take(&mut self.depth_of_field.enabled),
+            std::mem::take(&mut self.settings.jitter),
+            std::mem::take(&mut self.settings.occlusion_culling),
+            std::mem::take(&mut self.show_hud),
+            std::mem::take(&mut self.show_crosshair),
+        );
+        self.create_render_targets();
+
+        let eye = self.camera.world_position();
+        for (layer, rotation) in panorama::face_rotations().into_iter().enumerate() {
+            let camera = Camera {
+                position: rotation * eye,
+                rotation,
+                fov: std::f32::consts::FRAC_PI_2,
+                aspect: 1.0,
+                ..self.camera.clone()
+            };
+            self.begin_frame();
+            self.render_into(&panorama::face_view(&cube, layer as u32), &camera);
+        }
+
+        let config = self.config.as_mut().unwrap();
+        (config.width, config.height) = size;
+        (
+            self.motion_blur_enabled,
+            self.depth_of_field.enabled,
+            self.settings.jitter,
+            self.settings.occlusion_culling,
+            self.show_hud,
+            self.show_crosshair,
+        ) = effects;
+        self.create_render_targets();
+        panorama::reproject(self.device.as_ref().unwrap(), self.queue.as_ref().unwrap(), &cube)
+    }
+
+    /// Re-reads the gallery shaders from the source tree and rebuilds their
+    /// pipelines. A variant that fails to read or compile keeps its old pipeline.
+    fn reload_shaders(&mut self) {
+        let Some(format) = self.pipeline_format else { return };
+        let device = self.device.as_ref().unwrap();
+        let vertex_layout = Vertex::layout();
+        let mut reloaded = 0;
+        for (index, variant) in SHADER_VARIANTS.iter().enumerate() {
+            let pipelines = variant.read_source().map_err(|e| e.to_string()).and_then(|source| {
+                // Catch validation errors instead of letting the device's handler panic
+                device.push_error_scope(wgpu::ErrorFilter::Validation);
+                let single_sided = self.create_variant_pipeline(variant, &source, &vertex_layout, format, Some(wgpu::Face::Back));
+                let double_sided = self.create_variant_pipeline(variant, &source, &vertex_layout, format, None);
+                match pollster::block_on(device.pop_error_scope()) {
+                    Some(error) => Err(error.to_string()),
+                    None => Ok((single_sided, double_sided)),
+                }
+            });
+            match pipelines {
+                Ok((single_sided, double_sided)) => {
+                    self.pipelines[index] = single_sided;
+                    self.double_sided_pipelines[index] = double_sided;
+                    reloaded += 1;
+                }
+                Err(e) => eprintln!("Failed to reload shader {}, keeping the previous version: {e}", variant.name),
+            }
+        }
+        println!("Reloaded {reloaded} of {} shaders", SHADER_VARIANTS.len());
+    }
+
+    fn create_bounds_pipeline(&self, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        // Color writes are off, so any fragment stage will do
+        let variant = &SHADER_VARIANTS[0];
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bounds"),
+            source: wgpu::ShaderSource::Wgsl(variant.source().into()),
+        });
+        let state = PipelineState {
+            // Back faces keep the box visible when the camera is inside it
+            cull_mode: None,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            depth_write: false,
+            color_writes: wgpu::ColorWrites::empty(),
+            ..Default::default()
+        };
+        self.create_render_pipeline(&shader, variant.entry_point, &Vertex::layout(), format, &state)
+    }
+
+    /// Without `depth_test`, lines are drawn over everything so they stay visible as a whole.
+    fn create_line_pipeline(&self, format: wgpu::TextureFormat, depth_test: bool) -> wgpu::RenderPipeline {
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(LINE_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(LINE_SHADER.source().into()),
+        });
+        let state = PipelineState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            cull_mode: None,
+            depth_compare: if depth_test { wgpu::CompareFunction::LessEqual } else { wgpu::CompareFunction::Always },
+            depth_write: false,
+            ..Default::default()
+        };
+        self.create_render_pipeline(&shader, LINE_SHADER.entry_point, &Vertex::layout(), format, &state)
+    }
+
+    fn create_screen_line_pipeline(&self, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(LINE_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(LINE_SHADER.source().into()),
+        });
+        let state = PipelineState {
+            vertex_entry_point: "vs_screen",
+            topology: wgpu::PrimitiveTopology::LineList,
+            cull_mode: None,
+            overlay: true,
+            ..Default::default()
+        };
+        self.create_render_pipeline(&shader, "fs_screen", &Vertex::layout(), format, &state)
+    }
+
+    /// Edge-only pipeline for wireframe mode, if the device supports line polygons.
+    fn create_wireframe_pipeline(&self, format: wgpu::TextureFormat) -> Option<wgpu::RenderPipeline> {
+        if !self.device.as_ref().unwrap().features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            return None;
+        }
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(WIREFRAME_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(WIREFRAME_SHADER.source().into()),
+        });
+        // Back faces are kept so their edges show up in the back color
+        let state = PipelineState { polygon_mode: wgpu::PolygonMode::Line, cull_mode: None, ..Default::default() };
+        Some(self.create_render_pipeline(&shader, WIREFRAME_SHADER.entry_point, &Vertex::layout(), format, &state))
+    }
+
+    /// Pipeline coloring each triangle by its index, if the device can expose primitive indices.
+    fn create_triangle_pipeline(&self, format: wgpu::TextureFormat) -> Option<wgpu::RenderPipeline> {
+        if !self.device.as_ref().unwrap().features().contains(wgpu::Features::SHADER_PRIMITIVE_INDEX) {
+            return None;
+        }
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(TRIANGLE_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(TRIANGLE_SHADER.source().into()),
+        });
+        // Back faces are drawn (darkened) to reveal inconsistent winding
+        let state = PipelineState { cull_mode: None, ..Default::default() };
+        Some(self.create_render_pipeline(&shader, TRIANGLE_SHADER.entry_point, &Vertex::layout(), format, &state))
+    }
+
+    /// Pipelines for the selection outline, or `None` if the depth format has no stencil aspect.
+    fn create_outline_pipelines(&self, format: wgpu::TextureFormat) -> Option<OutlinePipelines> {
+        if !self.settings.depth_format.unwrap().has_stencil_aspect() {
+            return None;
+        }
+        let shader = self.device.as_ref().unwrap().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(OUTLINE_SHADER.name),
+            source: wgpu::ShaderSource::Wgsl(OUTLINE_SHADER.source().into()),
+        });
+        let stencil = |compare, pass_op, write_mask| {
+            let face = wgpu::StencilFaceState { compare, fail_op: wgpu::StencilOperation::Keep, depth_fail_op: wgpu::StencilOperation::Keep, pass_op };
+            wgpu::StencilState { front: face, back: face, read_mask: 0xff, write_mask }
+        };
+        // Both passes ignore depth so the outline traces the whole silhouette, even where occluded
+        let mark = PipelineState {
+            depth_compare: wgpu::CompareFunction::Always,
+            depth_write: false,
+            stencil: stencil(wgpu::CompareFunction::Always, wgpu::StencilOperation::Replace, 0xff),
+            color_writes: wgpu::ColorWrites::empty(),
+            ..Default::default()
+        };
+        let outline = PipelineState {
+            depth_compare: wgpu::CompareFunction::Always,
+            depth_write: false,
+            stencil: stencil(wgpu::CompareFunction::NotEqual, wgpu::StencilOperation::Keep, 0),
+            ..Default::default()
+        };
+        let vertex_layout = Vertex::layout();
+        Some(OutlinePipelines {
+            mark: self.create_render_pipeline(&shader, OUTLINE_SHADER.entry_point, &vertex_layout, format, &mark),
+            outline: self.create_render_pipeline(&shader, OUTLINE_SHADER.entry_point, &vertex_layout, format, &outline),
+        })
+    }
+
+    fn create_render_pipeline(
+        &self,
+        shader: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
+        vertex_layout: &VertexLayout,
+        format: wgpu::TextureFormat,
+        state: &PipelineState,
+    ) -> wgpu::RenderPipeline {
+        let device = self.device.as_ref().unwrap();
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&self.create_bind_group_layout(), &self.create_object_bind_group_layout()],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: state.vertex_entry_point,
+                buffers: &[vertex_layout.buffer_layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: fragment_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: state.color_writes,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: state.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: state.cull_mode,
+                polygon_mode: state.polygon_mode,
+                unclipped_depth: self.settings.unclipped_depth,
+                // Only filled triangles can be rasterized conservatively
+                conservative: self.settings.conservative
+                    && state.polygon_mode == wgpu::PolygonMode::Fill
+                    && state.topology == wgpu::PrimitiveTopology::TriangleList,
+            },
+            depth_stencil: (!state.overlay).then(|| wgpu::DepthStencilState {
+                format: self.settings.depth_format.unwrap(),
+                depth_write_enabled: state.depth_write,
+                depth_compare: state.depth_compare,
+                stencil: state.stencil.clone(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: if state.overlay { 1 } else { self.sample_count() },
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_bind_group_layout(&self) -> wgpu::BindGroupLayout {
+        self.device.as_ref().unwrap().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<LightUniforms>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    // Also samples the displacement map
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("uniform_bind_group_layout"),
+        })
+    }
+
+    fn create_object_bind_group_layout(&self) -> wgpu::BindGroupLayout {
+        self.device.as_ref().unwrap().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[ObjectBuffer::layout_entry()],
+            label: Some("object_bind_group_layout"),
+        })
+    }
+
+    /// World-space ray through a cursor position in window pixels.
+    fn cursor_ray(&self, cursor: Vec2) -> Option<(Vec3, Vec3)> {
+        let config = self.config.as_ref()?;
+        let ndc = Vec2::new(cursor.x / config.width as f32 * 2.0 - 1.0, 1.0 - cursor.y / config.height as f32 * 2.0);
+        Some(self.camera.ray(ndc))
+    }
+
+    /// Cursor position on the trackball's plane: the ball spans -1 to 1
+    /// across the smaller window dimension, centered in the window.
+    fn trackball_point(&self, cursor: Vec2) -> Option<Vec2> {
+        let config = self.config.as_ref()?;
+        let size = Vec2::new(config.width as f32, config.height as f32);
+        let centered = cursor * 2.0 - size;
+        Some(Vec2::new(centered.x, -centered.y) / size.min_element())
+    }
+
+    /// Selects the nearest visible object under the cursor, or clears the
+    /// selection. Objects are hit by their bounds, on the clip plane's kept side.
+    fn pick(&mut self, cursor: Vec2) {
+        let Some((origin, direction)) = self.cursor_ray(cursor) else { return };
+        let range = self.clip_plane.ray_range(origin, direction);
+        let world = scene::world_matrices(&self.objects);
+        self.selected = self
+            .visible_objects()
+            .filter_map(|index| {
+                let bounds = self.objects[index].bounds.transformed(world[index]);
+                Some((index, bounds.intersect_ray(origin, direction, range.clone()?)?))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index);
+        match self.selected {
+            Some(index) => {
+                println!("Selected {}", self.objects[index].name);
+                if self.outline_pipelines.is_none() {
+                    println!("Selection outlines need a depth format with stencil, e.g. --depth-format depth24plus-stencil8");
+                }
+            }
+            None => println!("Selection cleared"),
+        }
+    }
+
+    /// World-space point of the nearest visible surface under the cursor,
+    /// `None` over the background. Surfaces the clip plane cuts away are
+    /// skipped; height-map displacement is not applied, so on a displaced
+    /// model the point lies on the flat mesh under the drawn surface.
+    fn surface_point(&self, cursor: Vec2) -> Option<Vec3> {
+        let (origin, direction) = self.cursor_ray(cursor)?;
+        let range = self.clip_plane.ray_range(origin, direction)?;
+        let world = scene::world_matrices(&self.objects);
+        let distance = self
+            .visible_objects()
+            .filter_map(|index| self.objects[index].intersect_ray(world[index], origin, direction, range.clone()))
+            .min_by(f32::total_cmp)?;
+        Some(origin + direction * distance)
+    }
+
+    /// Distance the camera moves per scrolled line. With smart speed it is a
+    /// fraction of the distance to the surface at the window center, or to the
+    /// scene's center over the background, so moving takes about as many
+    /// steps in a tight corner as across a huge scene. Surfaces the clip
+    /// plane cuts away don't count, so the camera can zoom into the cut.
+    fn zoom_speed(&self) -> f32 {
+        let Some(config) = self.config.as_ref().filter(|_| self.settings.smart_speed) else {
+            return ZOOM_SPEED;
+        };
+        let center = Vec2::new(config.width as f32, config.height as f32) / 2.0;
+        let eye = self.camera.world_position();
+        let distance = self.surface_point(center).map_or(eye.length(), |point| eye.distance(point));
+        (distance * SMART_SPEED_FRACTION).clamp(MIN_SMART_SPEED, MAX_SMART_SPEED)
+    }
+
+    /// Adds the surface point under the cursor to the measurement, starting a
+    /// new one if two points were already picked.
+    fn measure(&mut self, cursor: Vec2) {
+        let Some(point) = self.surface_point(cursor) else {
+            println!("No surface under the cursor");
+            return;
+        };
+        if self.measurement.points.len() == 2 {
+            self.measurement = Measurement { enabled: true, ..Default::default() };
+        }
+        self.measurement.points.push(point);
+        match self.measurement.points[..] {
+            [start, end] => {
+                println!("Measured {:.4} from {start} to {end}", start.distance(end));
+                let line = Mesh::lines([(start, end)], MEASUREMENT_COLOR);
+                self.measurement.line = Some(MeshBuffers::new(self.device.as_ref().unwrap(), "Measurement", &line));
+            }
+            [start] => println!("Measuring from {start}"),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Focuses depth of field on the surface under the cursor, as
+    /// `surface_point` finds it; over the background the focus stays where it was.
+    fn focus_on(&mut self, cursor: Vec2) {
+        let Some(point) = self.surface_point(cursor) else {
+            println!("No surface under the cursor; focus unchanged");
+            return;
+        };
+        // Depth along the view axis, which is what the post pass compares against
+        self.depth_of_field.focal_distance = -self.camera.view_matrix().transform_point3(point).z;
+        println!("Focused at {:.3}", self.depth_of_field.focal_distance);
+    }
+
+    /// Pipeline replacing the active shader for all objects in a debug view, if one is on.
+    fn debug_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        let wireframe = self.wireframe_pipeline.as_ref().filter(|_| self.wireframe);
+        wireframe.or(self.triangle_pipeline.as_ref().filter(|_| self.triangle_colors))
+    }
+
+    /// Indices of the objects drawn this frame.
+    fn visible_objects(&self) -> std::ops::Range<usize> {
+        if self.carousel {
+            self.active_object..self.active_object + 1
+        } else {
+            0..self.objects.len()
+        }
+    }
+
+    /// Lays out the objects for the current mode and frames the camera on them.
+    pub fn arrange_objects(&mut self) {
+        if self.carousel {
+            scene::center(&mut self.objects[self.active_object]);
+        } else {
+            scene::layout_side_by_side(&mut self.objects);
+        }
+        if let Some(bounds) = self.visible_bounds() {
+            self.camera.frame(bounds.radius());
+            self.depth_of_field.focal_distance = self.camera.position.length();
+        }
+    }
+
+    /// World-space bounds of all visible objects, `None` for an empty scene.
+    fn visible_bounds(&self) -> Option<Aabb> {
+        let world = scene::world_matrices(&self.objects);
+        self.visible_objects()
+            .map(|index| self.objects[index].bounds.transformed(world[index]))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Switches each visible object to the level of detail for its distance from the camera.
+    fn update_lods(&mut self) {
+        let eye = self.camera.world_position();
+        let world = scene::world_matrices(&self.objects);
+        for index in self.visible_objects() {
+            let object = &mut self.objects[index];
+            let distance = object.bounds.transformed(world[index]).center().distance(eye);
+            if let Some(lod) = object.select_lod(distance) {
+                println!("{}: LOD {lod} at distance {distance:.1}", object.name);
+            }
+        }
+    }
+
+    /// Index of the object buffer slot holding object `index`'s occlusion bounds.
+    fn bounds_slot(&self, index: usize) -> usize {
+        self.objects.len() + 1 + index
+    }
+
+    /// Picks up occlusion results from an earlier frame.
+    fn poll_occlusion(&mut self) {
+        if let (Some(device), Some(occlusion)) = (&self.device, &mut self.occlusion)
+            && occlusion.poll(device)
+            && self.settings.occlusion_culling
+        {
+            println!("Occlusion culling: {} of {} objects visible", occlusion.visible_count(), self.objects.len());
+        }
+    }
+
+    /// Registers `tick` to run in `advance` and every benchmark frame, before
+    /// drawing, with the camera and the previous frame's duration in seconds.
+    /// It replaces any earlier callback.
+    pub fn on_tick(&mut self, tick: impl FnMut(&mut Camera, f32) + 'static) {
+        self.tick = Some(Box::new(tick));
+    }
+
+    /// Moves the scene on to a frame starting at `now`, `dt` after the
+    /// previous one: applies inertia, the tick callback and the showcase
+    /// orbit, and takes in finished background work. Call before each
+    /// windowed frame.
+    pub fn advance(&mut self, now: Instant, dt: Duration) {
+        self.frame_time = dt;
+        if self.frame_clock.replace(now).is_none() {
+            // The showcase timeout counts from the first frame, so that replays start the orbit on the same frame
+            self.showcase = self.settings.showcase.map(|timeout| IdleOrbit::new(timeout, now));
+        }
+        self.poll_texture_loader();
+        self.camera.position += self.dolly.advance(dt.as_secs_f32());
+        if let Some(tick) = &mut self.tick {
+            tick(&mut self.camera, self.animation_clock.delta(dt.as_secs_f32()));
+        }
+        if let Some(showcase) = &mut self.showcase {
+            let orbiting = showcase.is_orbiting();
+            let angle = showcase.advance(now);
+            if angle > 0.0 {
+                if !orbiting {
+                    println!("Idle; starting showcase orbit");
+                }
+                // Turns the world about its up axis, so the camera circles the scene center
+                self.camera.rotation *= Quat::from_rotation_y(self.animation_clock.delta(angle));
+            }
+        }
+        self.update_lods();
+        self.poll_occlusion();
+    }
+
+    /// Renders `frames` frames offscreen as fast as possible and prints frame
+    /// time statistics. Each frame is timed until the GPU has finished it.
+    pub fn run_benchmark(&mut self, frames: u32) {
+        let target = self.create_offscreen_target("Benchmark Target", wgpu::TextureUsages::empty());
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        println!("Benchmarking {frames} frames at {}x{}", target.width(), target.height());
+        let mut stats = FrameStats::default();
+        for _ in 0..frames {
+            let start = Instant::now();
+            if let Some(tick) = &mut self.tick {
+                tick(&mut self.camera, self.animation_clock.delta(self.frame_time.as_secs_f32()));
+            }
+            self.update_lods();
+            self.poll_occlusion();
+            self.begin_frame();
+            let camera = self.camera.clone();
+            self.render_into(&view, &camera);
+            self.device.as_ref().unwrap().poll(wgpu::Maintain::Wait);
+            self.frame_time = start.elapsed();
+            stats.record(self.frame_time);
+        }
+        match stats.summary() {
+            Some(summary) => println!("Benchmark: {summary}"),
+            None => println!("Benchmark: too few frames to summarize"),
+        }
+    }
+
+    /// A color target in the configured format and size, with `usage` on top
+    /// of `RENDER_ATTACHMENT`.
+    fn create_offscreen_target(&self, label: &str, usage: wgpu::TextureUsages) -> wgpu::Texture {
+        let config = self.config.as_ref().unwrap();
+        create_attachment(self.device.as_ref().unwrap(), label, config.format, (config.width, config.height), 1, usage)
+    }
+
+    /// Renders one offscreen frame of the cube and checks that it covers the
+    /// center pixel, after printing what the adapter and device offer.
+    /// Returns whether the check passed.
+    pub fn run_selftest(&mut self) -> bool {
+        let (Some(adapter), Some(device), Some(config)) = (&self.adapter, &self.device, &self.config) else {
+            println!("Self-test: FAIL, graphics did not initialize");
+            return false;
+        };
+        let info = adapter.get_info();
+        println!("Adapter: {} ({:?}, {:?}, driver {} {})", info.name, info.backend, info.device_type, info.driver, info.driver_info);
+        println!("Features granted: {:?}", device.features());
+        let limits = device.limits();
+        println!(
+            "Limits: 2D textures up to {}px, {} bind groups, {} byte uniform bindings, {} byte uniform offset alignment",
+            limits.max_texture_dimension_2d,
+            limits.max_bind_groups,
+            limits.max_uniform_buffer_binding_size,
+            limits.min_uniform_buffer_offset_alignment,
+        );
+        let sample_counts: Vec<u32> = [1, 2, 4, 8, 16]
+            .into_iter()
+            .filter(|&count| adapter.get_texture_format_features(config.format).flags.sample_count_supported(count))
+            .collect();
+        println!("Sample counts for {:?}: {sample_counts:?}", config.format);
+        use wgpu::TextureFormat::*;
+        let renderable: Vec<wgpu::TextureFormat> =
+            [Rgba8Unorm, Rgba8UnormSrgb, Bgra8Unorm, Bgra8UnormSrgb, Rgb10a2Unorm, Rgba16Float, Rgba32Float, Depth16Unorm, Depth24Plus, Depth24PlusStencil8, Depth32Float]
+                .into_iter()
+                .filter(|&format| {
+                    let features = adapter.get_texture_format_features(format);
+                    features.allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) && device.features().contains(format.required_features())
+                })
+                .collect();
+        println!("Renderable formats: {renderable:?}");
+
+        let target = self.create_offscreen_target("Self-test Target", wgpu::TextureUsages::COPY_SRC);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        self.begin_frame();
+        let camera = self.camera.clone();
+        self.render_into(&view, &camera);
+        let image = match Image::read_back(self.device.as_ref().unwrap(), self.queue.as_ref().unwrap(), &target) {
+            Ok(image) => image,
+            Err(e) => {
+                println!("Self-test: FAIL, could not read the frame back: {e}");
+                return false;
+            }
+        };
+        // The cube is framed at the center; the corners show the background
+        let pixel = |x: u32, y: u32| {
+            let start = ((y * image.width + x) * 4) as usize;
+            &image.pixels[start..start + 4]
+        };
+        let (center, background) = (pixel(image.width / 2, image.height / 2), pixel(0, 0));
+        if center == background {
+            println!("Self-test: FAIL, the center pixel {center:?} is the background color");
+            return false;
+        }
+        println!("Self-test: PASS, the center pixel {center:?} differs from the background {background:?}");
+        true
+    }
+
+    /// The configured color load op, except that a target not yet written
+    /// this frame is cleared (with the default color) instead of loaded.
+    /// With premultiplied compositing the clear color is premultiplied too;
+    /// everything drawn over it is opaque, so it needs no such care.
+    fn color_load_op(&self) -> wgpu::LoadOp<wgpu::Color> {
+        let [r, g, b] = match self.settings.color_load {
+            ColorLoad::Load if self.target_written => return wgpu::LoadOp::Load,
+            ColorLoad::Load => DEFAULT_CLEAR_COLOR,
+            ColorLoad::Clear(color) => color,
+        }
+        .map(|channel| self.output_encoding.apply(channel));
+        let a = self.settings.clear_alpha.unwrap_or(1.0);
+        let scale = if self.settings.alpha_mode == Some(wgpu::CompositeAlphaMode::PreMultiplied) { a } else { 1.0 };
+        wgpu::LoadOp::Clear(wgpu::Color { r: (r * scale) as f64, g: (g * scale) as f64, b: (b * scale) as f64, a: a as f64 })
+    }
+
+    /// The configured depth load op; like `color_load_op`, a target not yet
+    /// written this frame is cleared instead of loaded.
+    fn depth_load_op(&self) -> wgpu::LoadOp<f32> {
+        match self.settings.depth_load {
+            DepthLoad::Load if self.target_written => wgpu::LoadOp::Load,
+            DepthLoad::Load | DepthLoad::Clear => wgpu::LoadOp::Clear(1.0),
+        }
+    }
+
+    /// Starts a frame and draws and presents it from the window's camera;
+    /// fails if no swapchain frame can be acquired.
+    pub fn draw_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+        let frame = surface.get_current_texture()?;
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let camera = self.camera.clone();
+        self.begin_frame();
+        self.render_into(&view, &camera);
+        frame.present();
+
+        if let Some(startup) = self.startup.take() {
+            startup.finish();
+        }
+        Ok(())
+    }
+
+    /// Starts a frame for `render_into`: the frame's first call clears the
+    /// target and depth buffer even with `--clear load`, and the jitter
+    /// phase and motion blur history move on from the previous frame, if one
+    /// was drawn. `draw_frame` calls it itself.
+    pub fn begin_frame(&mut self) {
+        if self.target_written {
+            self.frame_index = self.frame_index.wrapping_add(1);
+            if let Some(post) = &mut self.post {
+                post.advance();
+            }
+        }
+        self.target_written = false;
+    }
+
+    /// Draws the scene as seen from `camera` into `view`, for hosts that
+    /// render into their own textures instead of the window. Call
+    /// `begin_frame` before each frame. The view must match
+    /// `target_config`'s format and size, since the pipelines and depth
+    /// buffer are shared with the window. With `--clear load`, calls after
+    /// the first in a frame draw over what the earlier ones left. Occlusion
+    /// and pipeline statistics queries only run in the first.
+    pub fn render_into(&mut self, view: &wgpu::TextureView, camera: &Camera) {
+        let first_in_frame = !self.target_written;
+        if self.frozen_frustum.is_none() {
+            self.cull_view_proj = camera.projection_matrix() * camera.view_matrix();
+        }
+        if let (Some(device), Some(queue), Some(config), Some(_), Some(object_buffer), Some(uniform_bind_group), Some(depth_view)) = 
+            (&self.device, &self.queue, &self.config, self.pipelines.get(self.active_shader), &self.object_buffer, &self.uniform_bind_group, &self.depth_view) {
+            
+            // Update uniforms
+            let jitter = if self.settings.jitter {
+                jitter::offset(self.frame_index, config.width, config.height)
+            } else {
+                Vec2::ZERO
+            };
+            let reflectivity = if self.reflective { REFLECTIVITY } else { 0.0 };
+            let uniforms = Uniforms::new(
+                camera,
+                jitter,
+                reflectivity,
+                &self.display,
+                &self.clip_plane,
+                &self.wireframe_colors,
+                self.displacement_scale,
+            )
+            .with_material(&self.material)
+            .with_output_encoding(self.output_encoding);
+            let uniform_ring = self.uniform_ring.as_mut().unwrap();
+            uniform_ring.write_next(queue, bytemuck::bytes_of(&uniforms));
+            let uniform_offset = uniform_ring.offset();
+            queue.write_buffer(self.light_buffer.as_ref().unwrap(), 0, bytemuck::bytes_of(&LightUniforms::new(&self.lights)));
+            let world = scene::world_matrices(&self.objects);
+            for index in self.visible_objects() {
+                object_buffer.write(queue, index, ObjectUniforms {
+                    tint: self.objects[index].tint.into(),
+                    ..ObjectUniforms::new(world[index])
+                });
+            }
+            let occlusion = self.occlusion.as_ref().filter(|_| self.settings.occlusion_culling && first_in_frame);
+            if occlusion.is_some() {
+                for index in self.visible_objects() {
+                    let model = OcclusionCulling::bounds_matrix(&self.objects[index].bounds, world[index]);
+                    object_buffer.write(queue, self.bounds_slot(index), ObjectUniforms::new(model));
+                }
+            }
+            let outline = self.selected.filter(|index| self.visible_objects().contains(index));
+            let outline_slot = self.objects.len();
+            if let Some(index) = outline {
+                let bounds = self.objects[index].bounds;
+                let scale = 1.0 + OUTLINE_WIDTH * bounds.radius() / bounds.size().min_element().max(f32::EPSILON);
+                let enlarge = Mat4::from_translation(bounds.center())
+                    * Mat4::from_scale(Vec3::splat(scale))
+                    * Mat4::from_translation(-bounds.center());
+                object_buffer.write(queue, outline_slot, ObjectUniforms::new(world[index] * enlarge));
+            }
+
+            let line_slot = self.objects.len() * 2 + 1;
+            let depth_test = self.settings.overlay_depth_test;
+            let lines: Vec<(&MeshBuffers, bool)> = [(&self.measurement.line, depth_test.measurement), (&self.frozen_frustum, depth_test.frustum)]
+                .into_iter()
+                .filter_map(|(line, depth_test)| Some((line.as_ref()?, depth_test)))
+                .collect();
+            if !lines.is_empty() {
+                object_buffer.write(queue, line_slot, ObjectUniforms::new(Mat4::IDENTITY));
+            }
+
+            let crosshair_slot = line_slot + 1;
+            let crosshair = self.crosshair.as_ref().filter(|_| self.show_crosshair);
+            if crosshair.is_some() {
+                // Centered on a pixel center so one-pixel lines cover whole pixels
+                let center = Vec2::new((config.width / 2) as f32 + 0.5, (config.height / 2) as f32 + 0.5);
+                let model = screen_to_clip(config.width, config.height) * Mat4::from_translation(center.extend(0.0));
+                object_buffer.write(queue, crosshair_slot, ObjectUniforms::new(model));
+            }
+
+            let frustum = Frustum::from_view_proj(self.cull_view_proj);
+            let drawn: Vec<usize> = self
+                .visible_objects()
+                .filter(|&index| {
+                    let object = &self.objects[index];
+                    match self.settings.frustum_culling {
+                        FrustumCulling::Box => frustum.intersects_aabb(&object.bounds.transformed(world[index])),
+                        FrustumCulling::Sphere => frustum.intersects_sphere(&object.bounding_sphere.transformed(world[index])),
+                    }
+                })
+                // Objects hidden behind others last frame are skipped; their bounds are still queried below
+                .filter(|&index| occlusion.is_none_or(|o| o.is_visible(index)))
+                .collect();
+
+            let depth_format = self.settings.depth_format.unwrap();
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+            // With post-processing on, the scene goes to an offscreen target first
+            let scene_view = self.post.as_ref().map_or(view, |post| post.scene_view());
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_view.as_ref().unwrap_or(scene_view),
+                        resolve_target: self.msaa_view.as_ref().map(|_| scene_view),
+                        ops: wgpu::Operations {
+                            load: self.color_load_op(),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: depth_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: self.depth_load_op(),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: depth_format.has_stencil_aspect().then_some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: occlusion.map(|o| &o.query_set),
+                });
+
+                if let Some(stats) = self.pipeline_stats.as_ref().filter(|_| first_in_frame) {
+                    render_pass.begin_pipeline_statistics_query(&stats.query_set, 0);
+                }
+                render_pass.set_bind_group(0, uniform_bind_group, &[uniform_offset]);
+                // The overdraw view draws the objects in a pass of its own
+                for &index in drawn.iter().filter(|_| self.overdraw.is_none()) {
+                    let shader = self.objects[index].shading.map_or(self.active_shader, ShadingModel::variant);
+                    render_pass.set_pipeline(if let Some(debug) = self.debug_pipeline() {
+                        debug
+                    } else if self.objects[index].double_sided {
+                        &self.double_sided_pipelines[shader]
+                    } else {
+                        &self.pipelines[shader]
+                    });
+                    render_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(index)]);
+                    self.objects[index].draw(&mut render_pass);
+                }
+                if let (Some(occlusion), Some(bounds_pipeline)) = (occlusion, &self.bounds_pipeline) {
+                    render_pass.set_pipeline(bounds_pipeline);
+                    for index in self.visible_objects() {
+                        render_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(self.bounds_slot(index))]);
+                        occlusion.query(&mut render_pass, index);
+                    }
+                }
+                if let (Some(index), Some(outline_pipelines)) = (outline, &self.outline_pipelines) {
+                    render_pass.set_stencil_reference(1);
+                    render_pass.set_pipeline(&outline_pipelines.mark);
+                    render_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(index)]);
+                    self.objects[index].draw(&mut render_pass);
+                    render_pass.set_pipeline(&outline_pipelines.outline);
+                    render_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(outline_slot)]);
+                    self.objects[index].draw(&mut render_pass);
+                }
+                if let (Some(on_top), Some(depth_tested)) = (&self.line_pipeline, &self.depth_tested_line_pipeline) {
+                    for &(line, depth_test) in &lines {
+                        render_pass.set_pipeline(if depth_test { depth_tested } else { on_top });
+                        render_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(line_slot)]);
+                        line.draw(&mut render_pass);
+                    }
+                }
+                if self.pipeline_stats.is_some() && first_in_frame {
+                    render_pass.end_pipeline_statistics_query();
+                }
+            }
+
+            if let Some(overdraw) = &self.overdraw {
+                {
+                    let mut count_pass = overdraw.begin_count(&mut encoder);
+                    count_pass.set_bind_group(0, uniform_bind_group, &[uniform_offset]);
+                    for &index in &drawn {
+                        count_pass.set_pipeline(overdraw.pipeline(self.objects[index].double_sided));
+                        count_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(index)]);
+                        self.objects[index].draw(&mut count_pass);
+                    }
+                }
+                overdraw.draw_heat(&mut encoder, scene_view);
+            }
+            if let Some(post) = &self.post {
+                let uniforms = PostUniforms {
+                    motion_blur: if self.motion_blur_enabled { self.motion_blur } else { 0.0 },
+                    focal_distance: self.depth_of_field.focal_distance,
+                    aperture: if self.depth_of_field.enabled { self.depth_of_field.aperture } else { 0.0 },
+                    near: camera.near,
+                    far: camera.far,
+                    _padding: [0.0; 3],
+                };
+                post.draw(&mut encoder, queue, view, uniforms);
+            }
+            if let Some(hud) = self.hud.as_ref().filter(|_| self.show_hud) {
+                let readout = Readout {
+                    position: camera.world_position(),
+                    rotation: camera.rotation,
+                    fov: camera.fov,
+                    frame_time: self.frame_time,
+                };
+                hud.draw(&mut encoder, queue, view, (config.width, config.height), &readout);
+            }
+            // Over the final frame, so post effects and the overdraw view leave it sharp
+            if let (Some(crosshair), Some(pipeline)) = (crosshair, &self.screen_line_pipeline) {
+                let mut overlay_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Crosshair Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                overlay_pass.set_pipeline(pipeline);
+                overlay_pass.set_bind_group(0, uniform_bind_group, &[uniform_offset]);
+                overlay_pass.set_bind_group(1, &object_buffer.bind_group, &[object_buffer.offset(crosshair_slot)]);
+                crosshair.draw(&mut overlay_pass);
+            }
+            let stats = self.pipeline_stats.as_mut().filter(|_| first_in_frame);
+            if let Some(stats) = stats {
+                stats.resolve(&mut encoder);
+            }
+            let culling = self.settings.occlusion_culling && first_in_frame;
+            let queried = self.visible_objects();
+            if let Some(occlusion) = self.occlusion.as_mut().filter(|_| culling) {
+                occlusion.resolve(&mut encoder, queried);
+            }
+
+            queue.submit(std::iter::once(encoder.finish()));
+            self.target_written = true;
+            if !first_in_frame {
+                return;
+            }
+            if let Some(occlusion) = &mut self.occlusion {
+                occlusion.map_results();
+            }
+            if let Some(stats) = &mut self.pipeline_stats {
+                stats.map_results();
+                stats.print(device);
+            }
+        }
+    }
+}
+
+/// Render target formats, in order of preference, and size without a window,
+/// e.g. for `--bench-frames`
+const OFFSCREEN_FORMATS: [wgpu::TextureFormat; 2] = [wgpu::TextureFormat::Rgba8UnormSrgb, wgpu::TextureFormat::Rgba8Unorm];
+const OFFSCREEN_SIZE: (u32, u32) = (1280, 720);
+
+/// Has a stencil aspect for selection outlines and is always renderable in WebGPU
+const DEFAULT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// Maps window pixels, with the origin at the top left and y down, to clip
+/// space, for screen-space overlays.
+fn screen_to_clip(width: u32, height: u32) -> Mat4 {
+    Mat4::orthographic_rh(0.0, width as f32, height as f32, 0.0, -1.0, 1.0)
+}
+
+/// The requested compositing mode if the surface supports it, otherwise the
+/// surface's preferred one.
+fn supported_alpha_mode(
+    modes: &[wgpu::CompositeAlphaMode],
+    requested: Option<wgpu::CompositeAlphaMode>,
+) -> wgpu::CompositeAlphaMode {
+    let preferred = modes[0];
+    match requested {
+        Some(requested) if modes.contains(&requested) => requested,
+        Some(requested) => {
+            println!("Alpha mode {requested:?} is not supported by this surface (supported: {modes:?}); falling back to {preferred:?}");
+            preferred
+        }
+        None => preferred,
+    }
+}
+
+/// The requested depth format if the adapter can render to it, otherwise
+/// `Depth32Float`, or the default for formats with a stencil aspect, so the
+/// fallback keeps selection outlines working if the request did. Both are
+/// always renderable in WebGPU.
+fn supported_depth_format(adapter: &wgpu::Adapter, requested: Option<wgpu::TextureFormat>) -> wgpu::TextureFormat {
+    let Some(requested) = requested else {
+        return DEFAULT_DEPTH_FORMAT;
+    };
+    let usages = adapter.get_texture_format_features(requested).allowed_usages;
+    if usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+        return requested;
+    }
+    let fallback = if requested.has_stencil_aspect() { DEFAULT_DEPTH_FORMAT } else { wgpu::TextureFormat::Depth32Float };
+    println!("Depth format {requested:?} is not supported by this adapter; falling back to {fallback:?}");
+    fallback
+}
+
+/// The largest sample count up to `requested` that every format supports
+/// (including resolving, for color formats), so an over-ambitious `--msaa`
+/// doesn't fail pipeline creation.
+fn supported_sample_count(adapter: &wgpu::Adapter, formats: &[wgpu::TextureFormat], requested: Option<u32>) -> u32 {
+    let Some(requested) = requested else { return 1 };
+    let supported = |count: u32| {
+        formats.iter().all(|&format| {
+            let flags = adapter.get_texture_format_features(format).flags;
+            let resolvable = format.is_depth_stencil_format() || flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+            flags.sample_count_supported(count) && (count == 1 || resolvable)
+        })
+    };
+    let count = [16, 8, 4, 2, 1].into_iter().find(|&count| count <= requested && supported(count)).unwrap();
+    if count != requested {
+        println!("{requested}x MSAA is not supported for {formats:?}; using {count}x instead");
+    }
+    count
+}
+
+/// A render target of `size`, with `usage` on top of `RENDER_ATTACHMENT`.
+fn create_attachment(
+    device: &Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    (width, height): (u32, u32),
+    sample_count: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | usage,
+        view_formats: &[],
+    })
+}
+
+/// Lists the available adapters and takes the one at `index` if there is
+/// one that can present to `surface`. Otherwise requests a hardware adapter,
+/// retrying with the software fallback adapter (e.g. on headless machines or
+/// in containers without a GPU); `fallback_first` tries them the other way
+/// round.
+fn request_adapter(
+    instance: &Instance,
+    surface: Option<&wgpu::Surface>,
+    index: Option<usize>,
+    fallback_first: bool,
+) -> Option<wgpu::Adapter> {
+    let mut adapters = instance.enumerate_adapters(wgpu::Backends::all());
+    for (i, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        println!("Adapter {i}: {} ({:?}, {:?}, driver {})", info.name, info.backend, info.device_type, info.driver);
+    }
+    if let Some(index) = index {
+        match adapters.get(index) {
+            Some(adapter) if surface.is_none_or(|surface| adapter.is_surface_supported(surface)) => {
+                println!("Using adapter {index}");
+                return Some(adapters.swap_remove(index));
+            }
+            Some(_) => println!("Adapter {index} can't present to the window; using the default adapter"),
+            None => println!("No adapter {index} ({} found); using the default adapter", adapters.len()),
+        }
+    }
+    let request = |force_fallback_adapter| {
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: surface,
+            force_fallback_adapter,
+        }))
+    };
+    if fallback_first && let Some(adapter) = request(true) {
+        println!("Safe mode: using software fallback {}", adapter.get_info().name);
+        return Some(adapter);
+    }
+    request(false).or_else(|| {
+        let adapter = request(true)?;
+        println!("No hardware adapter available; using software fallback {}", adapter.get_info().name);
+        Some(adapter)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_plane_shortens_rays() {
+        let plane = ClipPlane { enabled: true, axis: 1, offset: 0.5 };
+        // Looking down from above, the ray is kept until it crosses y = 0.5
+        assert_eq!(plane.ray_range(Vec3::new(0.0, 2.0, 0.0), Vec3::NEG_Y), Some(0.0..=1.5));
+        // Looking up from below, it starts there
+        assert_eq!(plane.ray_range(Vec3::new(0.0, -1.0, 0.0), Vec3::Y * 2.0), Some(0.75..=f32::INFINITY));
+        assert_eq!(plane.ray_range(Vec3::ZERO, Vec3::X), None);
+        assert_eq!(plane.ray_range(Vec3::ZERO, Vec3::NEG_Y), None);
+        let disabled = ClipPlane { enabled: false, ..plane };
+        assert_eq!(disabled.ray_range(Vec3::ZERO, Vec3::X), Some(0.0..=f32::INFINITY));
+    }
+
+    /// Camera at `eye` looking at `target` with +Y up. The view matrix rotates
+    /// before translating, so the eye goes in rotated.
+    fn look_at(camera: &Camera, eye: Vec3, target: Vec3) -> Camera {
+        let rotation = Quat::from_mat4(&Mat4::look_at_rh(eye, target, Vec3::Y));
+        Camera { position: rotation * eye, rotation, ..camera.clone() }
+    }
+
+    /// Which pixels differ from the top left one, the background.
+    fn coverage(image: &Image) -> Vec<bool> {
+        let background = &image.pixels[..4];
+        image.pixels.chunks(4).map(|pixel| pixel != background).collect()
+    }
+
+    /// Fraction of pixels covered in one mask but not at the horizontally
+    /// mirrored position in the other.
+    fn mirror_mismatch(a: &[bool], b: &[bool], width: usize) -> f32 {
+        let mismatched = a.iter().enumerate().filter(|&(i, &covered)| {
+            let (x, y) = (i % width, i / width);
+            covered != b[y * width + width - 1 - x]
+        });
+        mismatched.count() as f32 / a.len() as f32
+    }
+
+    #[test]
+    #[ignore = "needs a GPU or software adapter"]
+    fn mirrored_cameras_render_mirrored_cubes() {
+        let mut renderer = Renderer::new(Config { start: StartScene::Cube, ..Default::default() });
+        renderer.init_graphics(None).unwrap();
+        renderer.arrange_objects();
+        let config = renderer.target_config().unwrap();
+        let target = renderer.device().unwrap().create_texture(&wgpu::TextureDescriptor {
+            label: Some("Test Target"),
+            size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Above and in front of the cube, to either side, so neither view is symmetric by itself
+        let distance = renderer.camera.position.length();
+        let eye = Vec3::new(0.8, 0.5, 0.3).normalize() * distance;
+        let masks = [eye, eye * Vec3::new(-1.0, 1.0, 1.0)].map(|eye| {
+            let camera = look_at(&renderer.camera, eye, Vec3::ZERO);
+            renderer.begin_frame();
+            renderer.render_into(&view, &camera);
+            let image = Image::read_back(renderer.device().unwrap(), renderer.queue().unwrap(), &target).unwrap();
+            coverage(&image)
+        });
+
+        let width = target.width() as usize;
+        let covered = masks[0].iter().filter(|&&covered| covered).count() as f32 / masks[0].len() as f32;
+        assert!(covered > 0.05, "the cube covers {covered} of the image");
+        // Rasterization rules and multisampling only differ along the edges
+        let mismatch = mirror_mismatch(&masks[0], &masks[1], width);
+        assert!(mismatch < 0.002, "mirrored views differ in {mismatch} of the pixels");
+        let asymmetry = mirror_mismatch(&masks[0], &masks[0], width);
+        assert!(asymmetry > 0.01, "a single view is nearly symmetric ({asymmetry}), so the test proves little");
+    }
+}
@@ -0,0 +1,132 @@
+//! Detection of runs that didn't end cleanly: every run creates a marker
+//! file named after its process ID and removes it on a clean exit, so
+//! finding a marker whose process is gone means that run crashed or was
+//! killed, e.g. while initializing the GPU. Markers of instances that are
+//! still running are left alone. On Linux a marker also holds its process's
+//! start time, so one whose ID has since been reused by an unrelated process
+//! is still found; elsewhere such a reuse hides the crash.
+
+use std::path::{Path, PathBuf};
+
+const PREFIX: &str = "blink-running-";
+
+pub struct RunMarker {
+    path: PathBuf,
+}
+
+impl RunMarker {
+    /// Shared by every run of the app for the current user.
+    pub fn default_dir() -> PathBuf {
+        std::env::temp_dir()
+    }
+
+    /// Creates this run's marker in `dir`, returning whether an earlier run
+    /// had left one behind. Those markers are removed once reported, so a
+    /// crash only triggers the next run's safe mode. Failing to write the
+    /// marker only loses the detection.
+    pub fn create(dir: &Path) -> (Self, bool) {
+        let crashed = Self::remove_left_behind(dir);
+        let pid = std::process::id();
+        let path = dir.join(format!("{PREFIX}{pid}"));
+        let started = start_time(pid).map(|ticks| ticks.to_string()).unwrap_or_default();
+        if let Err(e) = std::fs::write(&path, started) {
+            eprintln!("Failed to write the startup marker {}: {e}", path.display());
+        }
+        (Self { path }, crashed)
+    }
+
+    /// Removes the marker at a clean exit.
+    pub fn remove(self) {
+        if let Err(e) = std::fs::remove_file(&self.path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            eprintln!("Failed to remove the startup marker {}: {e}", self.path.display());
+        }
+    }
+
+    /// Removes the markers in `dir` whose process has exited, returning
+    /// whether there were any.
+    fn remove_left_behind(dir: &Path) -> bool {
+        let Ok(entries) = std::fs::read_dir(dir) else { return false };
+        let mut found = false;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(pid) = name.to_str().and_then(|name| name.strip_prefix(PREFIX)).and_then(|pid| pid.parse().ok()) else {
+                continue;
+            };
+            let started = std::fs::read_to_string(entry.path()).ok().and_then(|ticks| ticks.parse().ok());
+            if !is_running(pid) || started.is_some_and(|ticks| start_time(pid) != Some(ticks)) {
+                found = true;
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+        found
+    }
+}
+
+/// When the process `pid` started, in clock ticks since boot: field 22 of
+/// `/proc/<pid>/stat`. `None` if it isn't running or that can't be told.
+fn start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // Field 2, the command name, is in parentheses and may contain spaces
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Whether the process `pid` is still running. Where that can't be told, it's
+/// assumed not to be, so that a crash isn't missed.
+fn is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else if cfg!(unix) {
+        std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    } else if cfg!(windows) {
+        std::process::Command::new("tasklist")
+            .args(["/NH", "/FI", &format!("PID eq {pid}")])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_left_behind_is_detected_once() {
+        let dir = std::env::temp_dir().join(format!("blink-marker-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first, crashed) = RunMarker::create(&dir);
+        assert!(!crashed);
+        // A marker whose process is still running is another open instance
+        let (second, crashed) = RunMarker::create(&dir);
+        assert!(!crashed);
+        drop(second);
+        first.remove();
+        // No process has this ID, like one that crashed
+        let dead = dir.join(format!("{PREFIX}{}", u32::MAX));
+        std::fs::write(&dead, "").unwrap();
+        let (third, crashed) = RunMarker::create(&dir);
+        assert!(crashed);
+        assert!(!dead.exists());
+        third.remove();
+        let (fourth, crashed) = RunMarker::create(&dir);
+        assert!(!crashed);
+        fourth.remove();
+        // This process's ID, but another start time: the ID was reused
+        if let Some(started) = start_time(std::process::id()) {
+            let reused = dir.join(format!("{PREFIX}{}", std::process::id()));
+            std::fs::write(&reused, (started + 1).to_string()).unwrap();
+            let (fifth, crashed) = RunMarker::create(&dir);
+            assert!(crashed);
+            fifth.remove();
+        }
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
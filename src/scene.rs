@@ -0,0 +1,397 @@
+//! Renderable objects and their per-object GPU data.
+
+use std::ops::RangeInclusive;
+
+use glam::{Mat4, Quat, Vec3, Vec4};
+use wgpu::util::DeviceExt;
+
+use crate::mesh::{self, Aabb, BoundingSphere, Mesh};
+use crate::shaders::SHADER_VARIANTS;
+
+/// Gap left between objects laid out side by side, relative to their radii.
+const LAYOUT_SPACING: f32 = 0.25;
+
+/// Local transform, relative to the parent object if there is one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Lighting an object is drawn with regardless of the active gallery shader,
+/// so a scene can mix e.g. an unlit backdrop with lit models.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShadingModel {
+    /// Vertex colors as they are
+    Unlit,
+    /// Diffuse lighting only
+    Lambert,
+    /// Diffuse lighting with specular highlights
+    BlinnPhong,
+    /// Face normals as colors
+    Normals,
+}
+
+impl ShadingModel {
+    pub const ALL: [ShadingModel; 4] = [ShadingModel::Unlit, ShadingModel::Lambert, ShadingModel::BlinnPhong, ShadingModel::Normals];
+
+    /// Name on the command line and in logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadingModel::Unlit => "unlit",
+            ShadingModel::Lambert => "lambert",
+            ShadingModel::BlinnPhong => "blinn-phong",
+            ShadingModel::Normals => "normals",
+        }
+    }
+
+    /// Index of the gallery shader implementing this model.
+    pub fn variant(self) -> usize {
+        let name = match self {
+            ShadingModel::Unlit => "flat",
+            ShadingModel::Lambert => "lit",
+            ShadingModel::BlinnPhong => "blinn-phong",
+            ShadingModel::Normals => "normals",
+        };
+        SHADER_VARIANTS.iter().position(|variant| variant.name == name).unwrap()
+    }
+}
+
+/// Vertex and index buffers uploaded from a `Mesh`.
+pub struct MeshBuffers {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl MeshBuffers {
+    pub fn new(device: &wgpu::Device, name: &str, mesh: &Mesh) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Vertex Buffer")),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} Index Buffer")),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self { vertex_buffer, index_buffer, num_indices: mesh.indices.len() as u32 }
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+pub struct RenderObject {
+    pub name: String,
+    mesh: MeshBuffers,
+    /// Lower-detail meshes and the camera distance from which each is used,
+    /// in increasing distance order
+    lods: Vec<(f32, MeshBuffers)>,
+    /// 0 for the full-detail mesh, otherwise 1 + an index into `lods`
+    active_lod: usize,
+    /// Bounds of the mesh in its own coordinates
+    pub bounds: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    /// Full-detail triangles in the mesh's own coordinates, for ray casts
+    triangles: Vec<[Vec3; 3]>,
+    pub transform: Transform,
+    /// Draw both sides of every triangle, for thin surfaces such as leaves or paper
+    pub double_sided: bool,
+    /// `None` to follow the active gallery shader
+    pub shading: Option<ShadingModel>,
+    /// Multiplies the base color in the shaded views; white leaves it
+    /// unchanged. Alpha is unused, since objects are drawn opaque
+    pub tint: Vec4,
+    /// Index of the parent in the object list; set through `set_parent` so
+    /// the hierarchy can't contain cycles
+    parent: Option<usize>,
+}
+
+impl RenderObject {
+    pub fn new(device: &wgpu::Device, name: impl Into<String>, mesh: &Mesh) -> Self {
+        let name = name.into();
+        Self {
+            mesh: MeshBuffers::new(device, &name, mesh),
+            lods: Vec::new(),
+            active_lod: 0,
+            name,
+            bounds: mesh.bounds().expect("mesh has no vertices"),
+            bounding_sphere: mesh.bounding_sphere().expect("mesh has no vertices"),
+            triangles: mesh.triangles(),
+            transform: Transform::default(),
+            double_sided: false,
+            shading: None,
+            tint: Vec4::ONE,
+            parent: None,
+        }
+    }
+
+    /// Adds a lower-detail mesh used when the camera is at least `distance` away.
+    pub fn add_lod(&mut self, device: &wgpu::Device, distance: f32, mesh: &Mesh) {
+        let name = format!("{} LOD {}", self.name, self.lods.len() + 1);
+        self.lods.push((distance, MeshBuffers::new(device, &name, mesh)));
+        self.lods.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    /// Picks the level of detail for a camera `distance` away and returns it
+    /// if it changed.
+    pub fn select_lod(&mut self, distance: f32) -> Option<usize> {
+        let distances: Vec<f32> = self.lods.iter().map(|(d, _)| *d).collect();
+        let lod = lod_level(&distances, distance);
+        (lod != std::mem::replace(&mut self.active_lod, lod)).then_some(lod)
+    }
+
+    /// Distance along a world-space ray to the nearest point on the object's
+    /// surface within `range`, given its world matrix. Height-map
+    /// displacement happens on the GPU, so the undisplaced surface is hit.
+    pub fn intersect_ray(&self, world: Mat4, origin: Vec3, direction: Vec3, range: RangeInclusive<f32>) -> Option<f32> {
+        // Distances along the ray are the same in both spaces since the
+        // direction is transformed without renormalizing
+        let to_local = world.inverse();
+        let (local_origin, local_direction) = (to_local.transform_point3(origin), to_local.transform_vector3(direction));
+        self.bounds.intersect_ray(local_origin, local_direction, range.clone())?;
+        mesh::intersect_triangles(&self.triangles, local_origin, local_direction, range)
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        match self.active_lod {
+            0 => self.mesh.draw(render_pass),
+            lod => self.lods[lod - 1].1.draw(render_pass),
+        }
+    }
+}
+
+/// Number of LOD thresholds (sorted ascending) that `distance` has passed.
+fn lod_level(thresholds: &[f32], distance: f32) -> usize {
+    thresholds.iter().take_while(|&&threshold| distance >= threshold).count()
+}
+
+/// Attaches `child` to `parent` (or detaches it with `None`), refusing links
+/// that would make an object its own ancestor.
+pub fn set_parent(objects: &mut [RenderObject], child: usize, parent: Option<usize>) -> Result<(), String> {
+    if let Some(index) = [Some(child), parent].into_iter().flatten().find(|&i| i >= objects.len()) {
+        return Err(format!("object index {index} is out of range"));
+    }
+    if let Some(parent) = parent {
+        let parents: Vec<_> = objects.iter().map(|o| o.parent).collect();
+        if is_ancestor(&parents, child, parent) {
+            return Err(format!(
+                "making {} the parent of {} would create a cycle",
+                objects[parent].name, objects[child].name
+            ));
+        }
+    }
+    objects[child].parent = parent;
+    Ok(())
+}
+
+/// Whether `ancestor` is `node` itself or appears on the way up from it.
+fn is_ancestor(parents: &[Option<usize>], ancestor: usize, node: usize) -> bool {
+    let mut current = Some(node);
+    while let Some(index) = current {
+        if index == ancestor {
+            return true;
+        }
+        current = parents[index];
+    }
+    false
+}
+
+/// World matrix of every object: its local transform premultiplied by the
+/// world matrices of all its ancestors.
+pub fn world_matrices(objects: &[RenderObject]) -> Vec<Mat4> {
+    resolve_hierarchy(&objects.iter().map(|o| (o.transform.matrix(), o.parent)).collect::<Vec<_>>())
+}
+
+fn resolve_hierarchy(nodes: &[(Mat4, Option<usize>)]) -> Vec<Mat4> {
+    let mut world: Vec<Option<Mat4>> = vec![None; nodes.len()];
+    for start in 0..nodes.len() {
+        // Walk up to the nearest resolved ancestor (or a root), then resolve
+        // back down, so parents are always evaluated before their children
+        // regardless of list order
+        let mut chain = vec![start];
+        while let Some(parent) = nodes[*chain.last().unwrap()].1 {
+            if world[parent].is_some() {
+                break;
+            }
+            debug_assert!(!chain.contains(&parent), "cycle in object hierarchy");
+            chain.push(parent);
+        }
+        for &index in chain.iter().rev() {
+            let (local, parent) = nodes[index];
+            let parent_world = parent.map_or(Mat4::IDENTITY, |p| world[p].unwrap());
+            world[index] = Some(parent_world * local);
+        }
+    }
+    world.into_iter().map(Option::unwrap).collect()
+}
+
+/// Places the root objects in a row along X, centered on the origin.
+/// Children keep their transforms and move with their parents.
+pub fn layout_side_by_side(objects: &mut [RenderObject]) {
+    let mut left = 0.0;
+    let mut right = 0.0;
+    for object in objects.iter_mut().filter(|o| o.parent.is_none()) {
+        let scale = object.transform.scale;
+        let radius = object.bounds.radius() * scale.max_element();
+        let center = object.transform.rotation * (object.bounds.center() * scale);
+        object.transform.translation = Vec3::new(left + radius, 0.0, 0.0) - center;
+        right = left + 2.0 * radius;
+        left = right + 2.0 * radius * LAYOUT_SPACING;
+    }
+    for object in objects.iter_mut().filter(|o| o.parent.is_none()) {
+        object.transform.translation.x -= right / 2.0;
+    }
+}
+
+/// Moves a single object so that its bounds are centered on the origin.
+pub fn center(object: &mut RenderObject) {
+    let scale = object.transform.scale;
+    object.transform.translation = -(object.transform.rotation * (object.bounds.center() * scale));
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectUniforms {
+    pub model: [[f32; 4]; 4],
+    /// Multiplies the base color; alpha is unused
+    pub tint: [f32; 4],
+}
+
+impl ObjectUniforms {
+    /// Untinted uniforms, e.g. for overlays
+    pub fn new(model: Mat4) -> Self {
+        Self { model: model.to_cols_array_2d(), tint: [1.0; 4] }
+    }
+}
+
+/// One `ObjectUniforms` slot per object in a single buffer, selected with a
+/// dynamic offset when drawing.
+pub struct ObjectBuffer {
+    buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    stride: wgpu::BufferAddress,
+}
+
+impl ObjectBuffer {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, capacity: usize) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = (std::mem::size_of::<ObjectUniforms>() as wgpu::BufferAddress).next_multiple_of(alignment);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Object Uniform Buffer"),
+            size: stride * capacity.max(1) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniforms>() as u64),
+                }),
+            }],
+            label: Some("object_bind_group"),
+        });
+        Self { buffer, bind_group, stride }
+    }
+
+    pub fn layout_entry() -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniforms>() as u64),
+            },
+            count: None,
+        }
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, index: usize, uniforms: ObjectUniforms) {
+        queue.write_buffer(&self.buffer, self.offset(index) as wgpu::BufferAddress, bytemuck::bytes_of(&uniforms));
+    }
+
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (self.stride * index as wgpu::BufferAddress) as wgpu::DynamicOffset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(x: f32) -> Mat4 {
+        Mat4::from_translation(Vec3::new(x, 0.0, 0.0))
+    }
+
+    #[test]
+    fn children_accumulate_parent_transforms() {
+        // planet orbits the origin, moon orbits the planet
+        let nodes = [(translation(10.0), None), (translation(2.0), Some(0)), (translation(0.5), Some(1))];
+        let world = resolve_hierarchy(&nodes);
+        let x: Vec<f32> = world.iter().map(|m| m.w_axis.x).collect();
+        assert_eq!(x, [10.0, 12.0, 12.5]);
+    }
+
+    #[test]
+    fn children_listed_before_parents() {
+        let nodes = [(translation(0.5), Some(1)), (translation(2.0), Some(2)), (translation(10.0), None)];
+        let world = resolve_hierarchy(&nodes);
+        let x: Vec<f32> = world.iter().map(|m| m.w_axis.x).collect();
+        assert_eq!(x, [12.5, 12.0, 10.0]);
+    }
+
+    #[test]
+    fn shading_models_have_distinct_shaders() {
+        let variants: Vec<usize> = ShadingModel::ALL.iter().map(|model| model.variant()).collect();
+        assert!(variants.iter().enumerate().all(|(i, v)| !variants[..i].contains(v)));
+    }
+
+    #[test]
+    fn cycles_are_detected() {
+        // 2 -> 1 -> 0
+        let parents = [None, Some(0), Some(1)];
+        // Parenting 0 to 2 would close the loop
+        assert!(is_ancestor(&parents, 0, 2));
+        assert!(is_ancestor(&parents, 1, 1));
+        assert!(!is_ancestor(&parents, 2, 0));
+    }
+
+    #[test]
+    fn lod_follows_distance_thresholds() {
+        let thresholds = [10.0, 20.0, 40.0];
+        let levels: Vec<usize> = [0.0, 9.9, 10.0, 25.0, 100.0].iter().map(|&d| lod_level(&thresholds, d)).collect();
+        assert_eq!(levels, [0, 0, 1, 2, 3]);
+        assert_eq!(lod_level(&[], 100.0), 0);
+    }
+
+    #[test]
+    fn parent_rotation_applies_to_child_offset() {
+        let spin = Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let world = resolve_hierarchy(&[(spin, None), (translation(1.0), Some(0))]);
+        let child = world[1].transform_point3(Vec3::ZERO);
+        assert!(child.abs_diff_eq(Vec3::new(0.0, 0.0, -1.0), 1e-6));
+    }
+}
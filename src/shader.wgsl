@@ -1,29 +1,187 @@
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) normal: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) view_depth: f32,
+    @location(3) uv: vec2<f32>,
 }
 
 struct Uniforms {
     view_proj: mat4x4<f32>,
+    near: f32,
+    far: f32,
+    // Sub-pixel NDC offset baked into view_proj this frame
+    jitter: vec2<f32>,
+    camera_position: vec3<f32>,
+    reflectivity: f32,
+    gamma: f32,
+    brightness: f32,
+    // 0 writes colors as is, 1 encodes them to sRGB, 2 decodes them from sRGB
+    output_encoding: u32,
+    // World-space plane (normal, offset); fragments on its negative side are
+    // discarded. All zeros when clipping is off
+    clip_plane: vec4<f32>,
+    // Wireframe edge colors; alpha is unused
+    wireframe_front: vec4<f32>,
+    wireframe_back: vec4<f32>,
+    // Object-space offset along the normal for a height of 1
+    displacement_scale: f32,
+    // Procedural material: checker cells per world unit, noise seed, and
+    // whether it replaces vertex colors
+    material_scale: f32,
+    material_seed: u32,
+    material_enabled: u32,
+    material_colors: array<vec4<f32>, 2>,
 }
 
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
+@group(0) @binding(1)
+var environment: texture_cube<f32>;
+@group(0) @binding(2)
+var environment_sampler: sampler;
+
+struct PointLight {
+    position: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+}
+
+// Array length must match MAX_LIGHTS in lights.rs
+struct Lights {
+    lights: array<PointLight, 8>,
+    count: u32,
+}
+
+@group(0) @binding(3)
+var<uniform> lights: Lights;
+@group(0) @binding(4)
+var base_texture: texture_2d<f32>;
+@group(0) @binding(5)
+var base_sampler: sampler;
+// Height in the red channel
+@group(0) @binding(6)
+var displacement_texture: texture_2d<f32>;
+
+struct ObjectUniforms {
+    model: mat4x4<f32>,
+    // Multiplies the base color; alpha is unused
+    tint: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> object: ObjectUniforms;
 
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.position = uniforms.view_proj * vec4<f32>(in.position, 1.0);
+    let height = textureSampleLevel(displacement_texture, base_sampler, in.uv, 0.0).r;
+    let position = in.position + in.normal * height * uniforms.displacement_scale;
+    let world_position = object.model * vec4<f32>(position, 1.0);
+    out.position = uniforms.view_proj * world_position;
     out.color = in.color;
+    out.world_position = world_position.xyz;
+    out.uv = in.uv;
+    // For a perspective projection clip-space w is the distance along the view axis
+    out.view_depth = out.position.w;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
-} 
\ No newline at end of file
+    clip(in.world_position);
+    return vec4<f32>(adjust_display(reflect_environment(base_color(in), in.world_position)), 1.0);
+}
+
+// Flat face normal from screen-space derivatives of the world position. It
+// always points towards the viewer, so the back faces of double-sided objects
+// are lit correctly without flipping it on `front_facing`
+fn face_normal(world_position: vec3<f32>) -> vec3<f32> {
+    return normalize(cross(dpdy(world_position), dpdx(world_position)));
+}
+
+// Mixes the environment seen along the reflected view ray into the base color
+fn reflect_environment(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let view = normalize(world_position - uniforms.camera_position);
+    let reflected = reflect(view, face_normal(world_position));
+    let env = textureSample(environment, environment_sampler, reflected).rgb;
+    return mix(color, env, uniforms.reflectivity);
+}
+
+// Display calibration applied to every shader's final color, followed by
+// the encoding the target format needs (see color_space.rs)
+fn adjust_display(color: vec3<f32>) -> vec3<f32> {
+    let adjusted = pow(max(color + uniforms.brightness, vec3<f32>(0.0)), vec3<f32>(1.0 / uniforms.gamma));
+    if uniforms.output_encoding == 1u {
+        return linear_to_srgb(adjusted);
+    }
+    if uniforms.output_encoding == 2u {
+        return srgb_to_linear(adjusted);
+    }
+    return adjusted;
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// Integer hash of a lattice cell; stable for a given seed
+fn hash_cell(cell: vec3<i32>, seed: u32) -> f32 {
+    let c = bitcast<vec3<u32>>(cell);
+    var h = (c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u) ^ (seed * 2654435761u);
+    h = (h ^ (h >> 16u)) * 0x45d9f3bu;
+    h = h ^ (h >> 16u);
+    return f32(h) / 4294967295.0;
+}
+
+// Smoothly interpolated hashes of the surrounding lattice cells, from 0 to 1
+fn value_noise(p: vec3<f32>, seed: u32) -> f32 {
+    let cell = vec3<i32>(floor(p));
+    let f = fract(p);
+    let t = f * f * (3.0 - 2.0 * f);
+    var corners: array<f32, 8>;
+    for (var i = 0; i < 8; i++) {
+        corners[i] = hash_cell(cell + vec3<i32>(i & 1, (i >> 1) & 1, (i >> 2) & 1), seed);
+    }
+    let x = vec4<f32>(
+        mix(corners[0], corners[1], t.x),
+        mix(corners[2], corners[3], t.x),
+        mix(corners[4], corners[5], t.x),
+        mix(corners[6], corners[7], t.x),
+    );
+    let y = vec2<f32>(mix(x.x, x.y, t.y), mix(x.z, x.w, t.y));
+    return mix(y.x, y.y, t.z);
+}
+
+// World-space checkerboard with noise on top, or the vertex color while the
+// material is off; tinted per object either way
+fn base_color(in: VertexOutput) -> vec3<f32> {
+    if uniforms.material_enabled == 0u {
+        return in.color * object.tint.rgb;
+    }
+    let p = in.world_position * uniforms.material_scale;
+    let cell = vec3<i32>(floor(p));
+    let checker = (cell.x + cell.y + cell.z) & 1;
+    let noise = value_noise(p * 4.0, uniforms.material_seed);
+    return uniforms.material_colors[checker].rgb * (0.8 + 0.2 * noise) * object.tint.rgb;
+}
+
+fn clip(world_position: vec3<f32>) {
+    if dot(uniforms.clip_plane.xyz, world_position) + uniforms.clip_plane.w < 0.0 {
+        discard;
+    }
+}
@@ -0,0 +1,58 @@
+/// Directory the shader sources are re-read from when reloading at runtime.
+const SOURCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+
+/// A fragment shader from the shader gallery. Its source is appended to
+/// `shader.wgsl`, which provides the shared vertex stage and helpers.
+pub struct ShaderVariant {
+    pub name: &'static str,
+    pub entry_point: &'static str,
+    /// Fragment source file relative to `src/`, `None` for entry points in `shader.wgsl` itself
+    path: Option<&'static str>,
+    source: &'static str,
+}
+
+/// A variant whose fragment source is embedded from `src/<path>`.
+macro_rules! variant {
+    ($name:literal, $entry_point:literal, $path:literal) => {
+        ShaderVariant { name: $name, entry_point: $entry_point, path: Some($path), source: include_str!($path) }
+    };
+}
+
+impl ShaderVariant {
+    pub fn source(&self) -> String {
+        format!("{}\n{}", include_str!("shader.wgsl"), self.source)
+    }
+
+    /// Like `source()`, but reads the current files from the source tree
+    /// instead of the copies embedded at build time.
+    pub fn read_source(&self) -> std::io::Result<String> {
+        let read = |path: &str| std::fs::read_to_string(format!("{SOURCE_DIR}/{path}"));
+        let fragment = self.path.map(read).transpose()?.unwrap_or_default();
+        Ok(format!("{}\n{}", read("shader.wgsl")?, fragment))
+    }
+}
+
+pub const SHADER_VARIANTS: &[ShaderVariant] = &[
+    ShaderVariant { name: "flat", entry_point: "fs_main", path: None, source: "" },
+    variant!("lit", "fs_lit", "shaders/lit.wgsl"),
+    variant!("blinn-phong", "fs_blinn_phong", "shaders/blinn_phong.wgsl"),
+    variant!("normals", "fs_normals", "shaders/normals.wgsl"),
+    variant!("uv", "fs_uv", "shaders/uv.wgsl"),
+    variant!("textured", "fs_textured", "shaders/textured.wgsl"),
+    variant!("depth", "fs_depth", "shaders/depth.wgsl"),
+];
+
+/// Solid color used for the selection outline.
+pub const OUTLINE_SHADER: ShaderVariant = variant!("outline", "fs_outline", "shaders/outline.wgsl");
+
+/// Unshaded vertex colors, for line overlays such as the measuring tool.
+pub const LINE_SHADER: ShaderVariant = variant!("line", "fs_line", "shaders/line.wgsl");
+
+/// Constant increments for additive blending, counting fragments per pixel.
+pub const OVERDRAW_SHADER: ShaderVariant = variant!("overdraw", "fs_overdraw", "shaders/overdraw.wgsl");
+
+/// Edges colored by whether their triangle faces the camera.
+pub const WIREFRAME_SHADER: ShaderVariant = variant!("wireframe", "fs_wireframe", "shaders/wireframe.wgsl");
+
+/// A distinct color per triangle, for spotting degenerate triangles and bad index buffers.
+pub const TRIANGLE_SHADER: ShaderVariant = variant!("triangles", "fs_triangles", "shaders/triangles.wgsl");
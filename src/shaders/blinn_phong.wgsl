@@ -0,0 +1,32 @@
+const KEY_LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.4, 0.8, 0.6);
+const AMBIENT: f32 = 0.2;
+const SPECULAR: f32 = 0.5;
+const SHININESS: f32 = 32.0;
+
+// Diffuse plus a specular highlight from the half vector between the light and view directions
+fn blinn_phong(n: vec3<f32>, to_light: vec3<f32>, to_eye: vec3<f32>) -> vec2<f32> {
+    let diffuse = max(dot(n, to_light), 0.0);
+    let specular = select(0.0, pow(max(dot(n, normalize(to_light + to_eye)), 0.0), SHININESS), diffuse > 0.0);
+    return vec2<f32>(diffuse, specular * SPECULAR);
+}
+
+@fragment
+fn fs_blinn_phong(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    let n = face_normal(in.world_position);
+    let to_eye = normalize(uniforms.camera_position - in.world_position);
+    let key = blinn_phong(n, normalize(KEY_LIGHT_DIRECTION), to_eye);
+    var diffuse = vec3<f32>(AMBIENT + (1.0 - AMBIENT) * key.x);
+    var specular = vec3<f32>(key.y);
+    for (var i = 0u; i < lights.count; i++) {
+        let point = lights.lights[i];
+        let to_light = point.position - in.world_position;
+        let distance = length(to_light);
+        let attenuation = 1.0 / (1.0 + (distance * distance) / (point.range * point.range));
+        let shade = blinn_phong(n, to_light / distance, to_eye);
+        diffuse += point.color * point.intensity * attenuation * shade.x;
+        specular += point.color * point.intensity * attenuation * shade.y;
+    }
+    let lit = base_color(in) * diffuse + specular;
+    return vec4<f32>(adjust_display(reflect_environment(lit, in.world_position)), 1.0);
+}
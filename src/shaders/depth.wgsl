@@ -0,0 +1,7 @@
+@fragment
+fn fs_depth(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    // Logarithmic so that nearby geometry isn't crushed into a single shade
+    let depth = log(in.view_depth / uniforms.near) / log(uniforms.far / uniforms.near);
+    return vec4<f32>(adjust_display(vec3<f32>(1.0 - clamp(depth, 0.0, 1.0))), 1.0);
+}
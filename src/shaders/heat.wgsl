@@ -0,0 +1,43 @@
+// Overdraw heat map; standalone, unlike the gallery shaders
+
+@group(0) @binding(0)
+var overdraw_count: texture_2d<f32>;
+
+// A single triangle covering the screen
+@vertex
+fn vs_heat(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+// Red channel increment per fragment in the count target
+const HEAT_STEP: f32 = 8.0 / 255.0;
+// Fragments per pixel shown at full heat; the count target saturates at 31
+const HEAT_MAX: f32 = 16.0;
+
+// Black for untouched pixels, then blue, green, yellow and red up to
+// HEAT_MAX, and white beyond
+@fragment
+fn fs_heat(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let count = round(textureLoad(overdraw_count, vec2<i32>(position.xy), 0).r / HEAT_STEP);
+    if count == 0.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    if count > HEAT_MAX {
+        return vec4<f32>(1.0);
+    }
+    let t = (count - 1.0) / (HEAT_MAX - 1.0) * 3.0;
+    let blue = vec3<f32>(0.1, 0.2, 1.0);
+    let green = vec3<f32>(0.1, 0.9, 0.2);
+    let yellow = vec3<f32>(1.0, 0.9, 0.1);
+    let red = vec3<f32>(1.0, 0.1, 0.05);
+    var color: vec3<f32>;
+    if t < 1.0 {
+        color = mix(blue, green, t);
+    } else if t < 2.0 {
+        color = mix(green, yellow, t - 1.0);
+    } else {
+        color = mix(yellow, red, t - 2.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
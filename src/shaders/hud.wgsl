@@ -0,0 +1,30 @@
+// Text overlay; standalone, unlike the gallery shaders
+
+// Overlay rectangle in NDC: left, top, right, bottom
+@group(0) @binding(0)
+var<uniform> rect: vec4<f32>;
+@group(0) @binding(1)
+var hud_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var hud_sampler: sampler;
+
+struct HudOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Triangle strip over `rect`
+@vertex
+fn vs_hud(@builtin(vertex_index) index: u32) -> HudOutput {
+    var out: HudOutput;
+    let uv = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    out.position = vec4<f32>(mix(rect.xy, rect.zw, uv), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Texels are premultiplied
+@fragment
+fn fs_hud(in: HudOutput) -> @location(0) vec4<f32> {
+    return textureSample(hud_texture, hud_sampler, in.uv);
+}
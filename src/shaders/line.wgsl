@@ -0,0 +1,24 @@
+@fragment
+fn fs_line(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    return vec4<f32>(adjust_display(in.color), 1.0);
+}
+
+// Screen-space overlays such as the crosshair: the object matrix maps
+// vertices straight to clip space, bypassing the camera (and its jitter)
+@vertex
+fn vs_screen(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = object.model * vec4<f32>(in.position, 1.0);
+    out.color = in.color;
+    out.world_position = out.position.xyz;
+    out.uv = in.uv;
+    out.view_depth = out.position.w;
+    return out;
+}
+
+// Like `fs_line`, but without a world position to clip against
+@fragment
+fn fs_screen(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(adjust_display(in.color), 1.0);
+}
@@ -0,0 +1,18 @@
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.4, 0.8, 0.6);
+const AMBIENT: f32 = 0.2;
+
+@fragment
+fn fs_lit(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    let n = face_normal(in.world_position);
+    var light = vec3<f32>(AMBIENT + (1.0 - AMBIENT) * max(dot(n, normalize(LIGHT_DIRECTION)), 0.0));
+    for (var i = 0u; i < lights.count; i++) {
+        let point = lights.lights[i];
+        let to_light = point.position - in.world_position;
+        let distance = length(to_light);
+        let attenuation = 1.0 / (1.0 + (distance * distance) / (point.range * point.range));
+        light += point.color * point.intensity * attenuation * max(dot(n, to_light / distance), 0.0);
+    }
+    let lit = base_color(in) * light;
+    return vec4<f32>(adjust_display(reflect_environment(lit, in.world_position)), 1.0);
+}
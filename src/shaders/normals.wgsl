@@ -0,0 +1,5 @@
+@fragment
+fn fs_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    return vec4<f32>(adjust_display(face_normal(in.world_position) * 0.5 + 0.5), 1.0);
+}
@@ -0,0 +1,7 @@
+const OUTLINE_COLOR: vec3<f32> = vec3<f32>(1.0, 0.6, 0.1);
+
+@fragment
+fn fs_outline(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    return vec4<f32>(adjust_display(OUTLINE_COLOR), 1.0);
+}
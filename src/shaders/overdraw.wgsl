@@ -0,0 +1,9 @@
+// Overdraw counting: every fragment adds one step to the red channel, with
+// additive blending and no depth test. Must match HEAT_STEP in heat.wgsl
+const OVERDRAW_STEP: f32 = 8.0 / 255.0;
+
+@fragment
+fn fs_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    return vec4<f32>(OVERDRAW_STEP, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,37 @@
+// Cubemap to equirectangular reprojection; standalone, unlike the gallery shaders
+
+@group(0) @binding(0)
+var cube: texture_cube<f32>;
+@group(0) @binding(1)
+var cube_sampler: sampler;
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// A single triangle covering the screen, with UVs from 0 to 1 across it
+@vertex
+fn vs_panorama(@builtin(vertex_index) index: u32) -> FullscreenOutput {
+    var out: FullscreenOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+const PI: f32 = 3.14159265;
+
+@fragment
+fn fs_panorama(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    // Longitude runs from -π at the left edge to π at the right, latitude
+    // from π/2 at the top to -π/2 at the bottom. UVs are interpolated at pixel
+    // centers, so no pixel lands exactly on a pole where longitude is undefined.
+    let longitude = (in.uv.x - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - in.uv.y) * PI;
+    let direction = vec3<f32>(sin(longitude) * cos(latitude), sin(latitude), -cos(longitude) * cos(latitude));
+    // The faces were rendered mirrored in X. An explicit level avoids the
+    // derivative jump where longitude wraps around, which would otherwise
+    // pick the wrong level along that seam and at the poles.
+    return textureSampleLevel(cube, cube_sampler, direction * vec3<f32>(-1.0, 1.0, 1.0), 0.0);
+}
@@ -0,0 +1,81 @@
+// Fullscreen post pass; standalone, unlike the gallery shaders
+
+struct PostUniforms {
+    // Weight of the previous output
+    motion_blur: f32,
+    focal_distance: f32,
+    // Blur radius in pixels far behind the focal plane, 0 without depth of field
+    aperture: f32,
+    near: f32,
+    far: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> post: PostUniforms;
+@group(0) @binding(1)
+var scene: texture_2d<f32>;
+@group(0) @binding(2)
+var history: texture_2d<f32>;
+@group(0) @binding(3)
+var post_sampler: sampler;
+// Bound as an unfilterable float texture, which unlike texture_depth_2d can
+// be loaded from on all backends; multisampled with MSAA
+@group(0) @binding(4)
+var scene_depth: texture_2d<f32>;
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// A single triangle covering the screen, with UVs from 0 to 1 across it
+@vertex
+fn vs_post(@builtin(vertex_index) index: u32) -> FullscreenOutput {
+    var out: FullscreenOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Largest blur radius in pixels
+const MAX_BLUR_RADIUS: f32 = 24.0;
+const BLUR_SAMPLES: u32 = 24u;
+const GOLDEN_ANGLE: f32 = 2.3999632;
+
+// View-space distance of the surface at a pixel
+fn linear_depth(pixel: vec2<i32>) -> f32 {
+    let depth = textureLoad(scene_depth, pixel, 0).r;
+    return post.near * post.far / (post.far - depth * (post.far - post.near));
+}
+
+fn depth_of_field(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(scene));
+    let distance = linear_depth(vec2<i32>(uv * size));
+    let radius = min(post.aperture * abs(distance - post.focal_distance) / distance, MAX_BLUR_RADIUS);
+    if radius < 0.5 {
+        return textureSampleLevel(scene, post_sampler, uv, 0.0);
+    }
+    // Samples on a golden-angle spiral cover the blur disc evenly
+    var sum = vec4<f32>(0.0);
+    for (var i = 0u; i < BLUR_SAMPLES; i++) {
+        let offset = sqrt((f32(i) + 0.5) / f32(BLUR_SAMPLES)) * radius;
+        let angle = f32(i) * GOLDEN_ANGLE;
+        sum += textureSampleLevel(scene, post_sampler, uv + vec2<f32>(cos(angle), sin(angle)) * offset / size, 0.0);
+    }
+    return sum / f32(BLUR_SAMPLES);
+}
+
+struct PostOutput {
+    @location(0) frame: vec4<f32>,
+    // Read back as the previous frame next time
+    @location(1) history: vec4<f32>,
+}
+
+@fragment
+fn fs_post(in: FullscreenOutput) -> PostOutput {
+    let current = depth_of_field(in.uv);
+    let previous = textureSampleLevel(history, post_sampler, in.uv, 0.0);
+    let color = mix(current, previous, post.motion_blur);
+    return PostOutput(color, color);
+}
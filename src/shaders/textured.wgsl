@@ -0,0 +1,6 @@
+@fragment
+fn fs_textured(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    let color = textureSample(base_texture, base_sampler, in.uv).rgb * object.tint.rgb;
+    return vec4<f32>(adjust_display(reflect_environment(color, in.world_position)), 1.0);
+}
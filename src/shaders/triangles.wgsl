@@ -0,0 +1,21 @@
+// Back faces are darkened so flipped winding stands out
+const BACK_FACE_SHADE: f32 = 0.4;
+
+// PCG-style integer hash, so neighboring triangles get unrelated colors
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+@fragment
+fn fs_triangles(
+    in: VertexOutput,
+    @builtin(primitive_index) primitive: u32,
+    @builtin(front_facing) front_facing: bool,
+) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    let bits = hash(primitive);
+    let color = vec3<f32>(vec3<u32>(bits, bits >> 8u, bits >> 16u) & vec3<u32>(255u)) / 255.0;
+    return vec4<f32>(adjust_display(color * select(BACK_FACE_SHADE, 1.0, front_facing)), 1.0);
+}
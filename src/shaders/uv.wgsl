@@ -0,0 +1,5 @@
+@fragment
+fn fs_uv(in: VertexOutput) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    return vec4<f32>(adjust_display(vec3<f32>(in.uv, 0.0)), 1.0);
+}
@@ -0,0 +1,6 @@
+@fragment
+fn fs_wireframe(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    clip(in.world_position);
+    let color = select(uniforms.wireframe_back.rgb, uniforms.wireframe_front.rgb, front_facing);
+    return vec4<f32>(adjust_display(color), 1.0);
+}
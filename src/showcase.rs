@@ -0,0 +1,81 @@
+//! Showcase mode: after a while without input the camera slowly orbits the
+//! scene, so `blink` can run as a passive viewer. Any input hands control back.
+
+use std::time::{Duration, Instant};
+
+/// Orbit speed once it is up to speed, in radians per second
+const ORBIT_SPEED: f32 = 0.3;
+/// Time to accelerate from standing still to `ORBIT_SPEED`
+const EASE_IN: Duration = Duration::from_secs(2);
+
+pub struct IdleOrbit {
+    timeout: Duration,
+    last_input: Instant,
+    last_frame: Instant,
+    /// Whether the last frame moved the camera
+    orbiting: bool,
+}
+
+impl IdleOrbit {
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self { timeout, last_input: now, last_frame: now, orbiting: false }
+    }
+
+    /// Records input at `now`; returns whether this stopped the orbit.
+    pub fn input(&mut self, now: Instant) -> bool {
+        self.last_input = now;
+        std::mem::take(&mut self.orbiting)
+    }
+
+    pub fn is_orbiting(&self) -> bool {
+        self.orbiting
+    }
+
+    /// Angle in radians to orbit by for a frame drawn at `now`.
+    pub fn advance(&mut self, now: Instant) -> f32 {
+        let previous = std::mem::replace(&mut self.last_frame, now);
+        let start = self.last_input + self.timeout;
+        let travelled = |time: Instant| {
+            // Speed ramps up linearly during the ease-in, so the distance grows quadratically
+            let t = time.saturating_duration_since(start).as_secs_f32();
+            let ease_in = EASE_IN.as_secs_f32();
+            if t < ease_in { ORBIT_SPEED * t * t / (2.0 * ease_in) } else { ORBIT_SPEED * (t - ease_in / 2.0) }
+        };
+        let angle = travelled(now) - travelled(previous);
+        self.orbiting = angle > 0.0;
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(s: f32) -> Duration {
+        Duration::from_secs_f32(s)
+    }
+
+    #[test]
+    fn orbit_starts_after_timeout_and_eases_in() {
+        let start = Instant::now();
+        let mut orbit = IdleOrbit::new(seconds(10.0), start);
+        assert_eq!(orbit.advance(start + seconds(9.0)), 0.0);
+        assert!(!orbit.is_orbiting());
+        let first = orbit.advance(start + seconds(11.0));
+        let second = orbit.advance(start + seconds(12.0));
+        assert!(first > 0.0 && second > first);
+        // At full speed
+        let later = orbit.advance(start + seconds(22.0));
+        assert!((later - ORBIT_SPEED * 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn input_stops_the_orbit() {
+        let start = Instant::now();
+        let mut orbit = IdleOrbit::new(seconds(1.0), start);
+        orbit.advance(start + seconds(5.0));
+        assert!(orbit.input(start + seconds(5.0)));
+        assert_eq!(orbit.advance(start + seconds(5.5)), 0.0);
+        assert!(!orbit.input(start + seconds(5.5)));
+    }
+}
@@ -0,0 +1,309 @@
+//! 2D base-color textures, decoded from PNG, JPEG or PPM files on a background
+//! thread, and PNG export of rendered images.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// Side length of the checkerboard shown while a texture loads.
+const PLACEHOLDER_SIZE: u32 = 8;
+
+/// Decoded RGBA8 image.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A single white texel, which leaves colors unchanged when multiplied in.
+    pub fn white() -> Self {
+        Self { width: 1, height: 1, pixels: vec![255; 4] }
+    }
+
+    /// A single black texel, e.g. a height map without any displacement.
+    pub fn black() -> Self {
+        Self { width: 1, height: 1, pixels: vec![0, 0, 0, 255] }
+    }
+
+    /// A gray checkerboard, shown until the real texture is ready.
+    pub fn placeholder() -> Self {
+        let pixels = (0..PLACEHOLDER_SIZE * PLACEHOLDER_SIZE)
+            .flat_map(|i| {
+                let shade = if (i % PLACEHOLDER_SIZE + i / PLACEHOLDER_SIZE).is_multiple_of(2) { 96 } else { 160 };
+                [shade, shade, shade, 255]
+            })
+            .collect();
+        Self { width: PLACEHOLDER_SIZE, height: PLACEHOLDER_SIZE, pixels }
+    }
+
+    /// Shrinks the image so neither side exceeds `max_size`, keeping the
+    /// aspect ratio, with a linear filter.
+    pub fn downscaled(&self, max_size: u32) -> Image {
+        let scale = max_size as f64 / self.width.max(self.height) as f64;
+        let width = ((self.width as f64 * scale).round() as u32).clamp(1, max_size);
+        let height = ((self.height as f64 * scale).round() as u32).clamp(1, max_size);
+        let source = image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone()).expect("pixel data matches the size");
+        let resized = image::imageops::resize(&source, width, height, image::imageops::FilterType::Triangle);
+        Image { width, height, pixels: resized.into_raw() }
+    }
+
+    /// Reads and decodes an image file in any format the `image` crate was
+    /// built with: PNG, JPEG or PPM.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| e.to_string())?.into_rgba8();
+        Ok(Self { width: image.width(), height: image.height(), pixels: image.into_raw() })
+    }
+
+    /// Copies an 8-bit RGBA or BGRA texture back from the GPU, waiting for it.
+    /// The texture needs `COPY_SRC` usage.
+    pub fn read_back(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Self, String> {
+        use wgpu::TextureFormat::*;
+        let swap_red_blue = match texture.format() {
+            Rgba8Unorm | Rgba8UnormSrgb => false,
+            Bgra8Unorm | Bgra8UnormSrgb => true,
+            format => return Err(format!("cannot read back {format:?} textures")),
+        };
+        let (width, height) = (texture.width(), texture.height());
+        // Rows of a texture copy are padded to a multiple of 256 bytes
+        let row_bytes = width as usize * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_row_bytes * height as usize) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Readback Encoder") });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(padded_row_bytes as u32), rows_per_image: None },
+            },
+            texture.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+        let (sender, receiver) = mpsc::channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("failed to map readback buffer: {e}"))?;
+        let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+        for row in buffer.slice(..).get_mapped_range().chunks_exact(padded_row_bytes) {
+            pixels.extend_from_slice(&row[..row_bytes]);
+        }
+        if swap_red_blue {
+            pixels.chunks_exact_mut(4).for_each(|texel| texel.swap(0, 2));
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Encodes the image as an 8-bit RGBA PNG. The pixel data is stored
+    /// uncompressed, which every decoder reads and keeps this short.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width as usize * 4 + 1) * self.height as usize);
+        for row in self.pixels.chunks_exact(self.width as usize * 4) {
+            // Filter type: none
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        // A zlib stream of stored deflate blocks
+        let mut zlib = vec![0x78, 0x01];
+        let blocks = raw.chunks(u16::MAX as usize);
+        let count = blocks.len();
+        for (i, block) in blocks.enumerate() {
+            let len = block.len() as u16;
+            zlib.push((i + 1 == count) as u8);
+            zlib.extend(len.to_le_bytes());
+            zlib.extend((!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // Bit depth 8, RGBA, deflate, no filtering beyond the per-row one, not interlaced
+        header.extend([8, 6, 0, 0, 0]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &zlib);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// CRC-32 as used by PNG (and zip), computed bit by bit.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 })
+    })
+}
+
+/// The zlib stream checksum.
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % MODULUS;
+        (a, (b + a) % MODULUS)
+    });
+    (b << 16) | a
+}
+
+pub struct Texture {
+    _texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl Texture {
+    /// A color texture; texels are decoded from sRGB when sampled.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, label: &str, image: &Image) -> Self {
+        Self::with_format(device, queue, label, image, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    /// A texture holding data rather than colors, such as a height map; texels are sampled as stored.
+    pub fn linear(device: &wgpu::Device, queue: &wgpu::Queue, label: &str, image: &Image) -> Self {
+        Self::with_format(device, queue, label, image, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn with_format(device: &wgpu::Device, queue: &wgpu::Queue, label: &str, image: &Image, format: wgpu::TextureFormat) -> Self {
+        let size = wgpu::Extent3d { width: image.width, height: image.height, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &image.pixels,
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(image.width * 4), rows_per_image: None },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { _texture: texture, view }
+    }
+
+    /// Repeating, linearly filtered sampler. `anisotropy` of 1 disables anisotropic filtering.
+    pub fn create_sampler(device: &wgpu::Device, anisotropy: u16) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            // Anisotropic filtering requires every filter to be linear
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: anisotropy,
+            ..Default::default()
+        })
+    }
+}
+
+/// Reads and decodes an image on its own thread; poll it once per frame.
+pub struct TextureLoader {
+    pub path: PathBuf,
+    receiver: mpsc::Receiver<Result<Image, String>>,
+}
+
+impl TextureLoader {
+    pub fn spawn(path: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.clone();
+        std::thread::spawn(move || {
+            let image = Image::open(&thread_path);
+            // The receiver is gone if the app quit before loading finished
+            let _ = sender.send(image);
+        });
+        Self { path, receiver }
+    }
+
+    /// The decoded image once loading has finished, or `None` while it's still running.
+    pub fn poll(&self) -> Option<Result<Image, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err("loader thread panicked".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `bytes` to a file named `name` in a fresh temporary directory and opens it.
+    fn open_bytes(name: &str, bytes: &[u8]) -> Result<Image, String> {
+        let dir = std::env::temp_dir().join(format!("blink-texture-test-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        let image = Image::open(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        image
+    }
+
+    #[test]
+    fn decodes_ppm_with_comments() {
+        let mut bytes = b"P6\n# made by hand\n2 1\n255\n".to_vec();
+        bytes.extend([255, 0, 0, 0, 0, 255]);
+        let image = open_bytes("comments.ppm", &bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, [255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn decodes_exported_png() {
+        let image = Image { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+        let decoded = open_bytes("exported.png", &image.to_png()).unwrap();
+        assert_eq!((decoded.width, decoded.height), (2, 1));
+        assert_eq!(decoded.pixels, image.pixels);
+    }
+
+    #[test]
+    fn downscaling_filters_and_keeps_aspect() {
+        // 8x4: left half black, right half white
+        let pixels = (0..32).flat_map(|i| if i % 8 < 4 { [0, 0, 0, 255] } else { [255; 4] }).collect();
+        let image = Image { width: 8, height: 4, pixels }.downscaled(2);
+        assert_eq!((image.width, image.height), (2, 1));
+        assert!(image.pixels[0] < 64 && image.pixels[4] > 192, "{:?}", image.pixels);
+        let gray = Image { width: 3, height: 1, pixels: vec![90; 12] };
+        assert_eq!(gray.downscaled(1).pixels, [90; 4]);
+    }
+
+    #[test]
+    fn png_chunks_and_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        let image = Image { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+        let png = image.to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01\x08\x06"));
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+        // The single stored block holds the filter byte and the row as is
+        let stored = [1, 9, 0, !9, !0, 0, 255, 0, 0, 255, 0, 0, 255, 128];
+        assert!(png.windows(stored.len()).any(|window| window == stored));
+    }
+
+    #[test]
+    fn rejects_truncated_ppm() {
+        assert!(open_bytes("truncated.ppm", b"P6 2 2 255\n\0\0\0").is_err());
+        assert!(open_bytes("not-an-image.png", b"P6 1 1 255\n\0\0\0").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Per-frame timing written as CSV for `--trace-csv`, to plot stutter over a
+//! session in a spreadsheet or plotting tool.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often buffered rows are written out, so a crash loses at most this much
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct FrameTrace<W: Write = BufWriter<File>> {
+    writer: W,
+    start: Instant,
+    last_flush: Instant,
+    frames: u64,
+}
+
+impl FrameTrace {
+    pub fn create(path: &Path, start: Instant) -> std::io::Result<Self> {
+        FrameTrace::new(BufWriter::new(File::create(path)?), start)
+    }
+}
+
+impl<W: Write> FrameTrace<W> {
+    /// Writes the header row; frame times are relative to `start`.
+    pub fn new(mut writer: W, start: Instant) -> std::io::Result<Self> {
+        writeln!(writer, "frame,time_s,frame_time_ms")?;
+        Ok(Self { writer, start, last_flush: start, frames: 0 })
+    }
+
+    /// Appends a frame that started at `now`, `frame_time` after the previous one.
+    pub fn record(&mut self, now: Instant, frame_time: Duration) {
+        let seconds = now.saturating_duration_since(self.start).as_secs_f64();
+        let result = writeln!(self.writer, "{},{seconds:.6},{:.3}", self.frames, frame_time.as_secs_f64() * 1000.0).and_then(|()| {
+            if now.saturating_duration_since(self.last_flush) < FLUSH_INTERVAL {
+                return Ok(());
+            }
+            self.last_flush = now;
+            self.writer.flush()
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to write frame trace: {e}");
+        }
+        self.frames += 1;
+    }
+
+    /// Writes out the remaining rows; call before exiting.
+    pub fn finish(mut self) {
+        match self.writer.flush() {
+            Ok(()) => println!("Traced {} frames", self.frames),
+            Err(e) => eprintln!("Failed to write frame trace: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_header_and_one_row_per_frame() {
+        let start = Instant::now();
+        let mut output = Vec::new();
+        let mut trace = FrameTrace::new(&mut output, start).unwrap();
+        trace.record(start + Duration::from_millis(16), Duration::from_millis(16));
+        trace.record(start + Duration::from_millis(50), Duration::from_micros(33_500));
+        trace.finish();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "frame,time_s,frame_time_ms\n0,0.016000,16.000\n1,0.050000,33.500\n"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Trackball (arcball) rotation: the cursor is projected onto a virtual
+//! sphere in front of the viewer, and dragging rotates the scene by the arc
+//! between where the drag started and where the cursor is now.
+
+use glam::{EulerRot, Quat, Vec2, Vec3};
+
+/// Point on the trackball under `p`, a cursor position scaled so the ball
+/// spans -1 to 1 across the smaller window dimension (Y up). Outside the ball
+/// the sphere continues as a hyperbolic sheet, so rotation stays smooth when
+/// dragging past its edge (Bell's trackball).
+pub fn project_to_sphere(p: Vec2) -> Vec3 {
+    let r2 = p.length_squared();
+    let z = if r2 <= 0.5 { (1.0 - r2).sqrt() } else { 0.5 / r2.sqrt() };
+    p.extend(z).normalize()
+}
+
+/// Shortest rotation turning unit vector `from` into unit vector `to`.
+pub fn arc_rotation(from: Vec3, to: Vec3) -> Quat {
+    let cos = from.dot(to);
+    if cos < -1.0 + 1e-6 {
+        // Opposite vectors: half a turn about any perpendicular axis
+        let axis = from.cross(Vec3::X).try_normalize().unwrap_or_else(|| from.cross(Vec3::Y).normalize());
+        return Quat::from_axis_angle(axis, std::f32::consts::PI);
+    }
+    // The quaternion (from x to, 1 + from . to) is the rotation by twice
+    // the angle between them, halved by normalizing
+    let axis = from.cross(to);
+    Quat::from_xyzw(axis.x, axis.y, axis.z, 1.0 + cos).normalize()
+}
+
+/// `rotation` (a camera rotation, which turns the world into view space)
+/// without its roll: the view direction is kept, and the view's right axis
+/// is brought back level with the world's XZ plane. Both camera modes can
+/// pile up roll, look mode through its view-space yaw and pitch.
+pub fn level_roll(rotation: Quat) -> Quat {
+    // The camera's orientation in the world is the inverse rotation, which
+    // is yaw about world Y, then pitch, then roll about the view direction
+    let (yaw, pitch, _roll) = rotation.inverse().to_euler(EulerRot::YXZ);
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0).inverse()
+}
+
+/// State of a trackball drag in progress.
+#[derive(Clone, Copy, Debug)]
+pub struct TrackballDrag {
+    start: Vec3,
+    start_rotation: Quat,
+}
+
+impl TrackballDrag {
+    pub fn new(start: Vec2, start_rotation: Quat) -> Self {
+        Self { start: project_to_sphere(start), start_rotation }
+    }
+
+    /// Camera rotation with the cursor at `current`.
+    pub fn rotation(&self, current: Vec2) -> Quat {
+        arc_rotation(self.start, project_to_sphere(current)) * self.start_rotation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arc_rotation_maps_from_onto_to() {
+        let quarter = arc_rotation(Vec3::X, Vec3::Y);
+        assert!(quarter.abs_diff_eq(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2), 1e-6));
+        let from = Vec3::new(0.3, -0.2, 0.9).normalize();
+        let to = Vec3::new(-0.5, 0.4, 0.6).normalize();
+        assert!((arc_rotation(from, to) * from).abs_diff_eq(to, 1e-6));
+        assert!((arc_rotation(Vec3::Z, Vec3::NEG_Z) * Vec3::Z).abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        assert!(arc_rotation(to, to).abs_diff_eq(Quat::IDENTITY, 1e-6));
+    }
+
+    #[test]
+    fn leveling_keeps_the_view_direction_and_levels_the_horizon() {
+        let level = Quat::from_rotation_x(0.4) * Quat::from_rotation_y(-1.1);
+        let rolled = Quat::from_rotation_z(0.7) * level;
+        let leveled = level_roll(rolled);
+        let forward = |rotation: Quat| rotation.inverse() * Vec3::NEG_Z;
+        assert!(forward(leveled).abs_diff_eq(forward(rolled), 1e-5));
+        let (right, up) = (leveled.inverse() * Vec3::X, leveled.inverse() * Vec3::Y);
+        assert!(right.y.abs() < 1e-5);
+        assert!(up.y > 0.0);
+        assert!(leveled.abs_diff_eq(level, 1e-5) || leveled.abs_diff_eq(-level, 1e-5));
+    }
+
+    #[test]
+    fn sphere_projection_is_continuous_at_the_edge() {
+        assert_eq!(project_to_sphere(Vec2::ZERO), Vec3::Z);
+        let edge = 0.5_f32.sqrt();
+        let inside = project_to_sphere(Vec2::new(edge - 1e-4, 0.0));
+        let outside = project_to_sphere(Vec2::new(edge + 1e-4, 0.0));
+        assert!(inside.abs_diff_eq(outside, 1e-3));
+    }
+}
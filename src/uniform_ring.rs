@@ -0,0 +1,70 @@
+//! A ring of uniform slots in one buffer, bound with a dynamic offset. Each
+//! frame writes the next slot, so the write doesn't land in a slot the GPU
+//! may still be reading for an earlier frame, which some drivers resolve by
+//! stalling until that frame is done.
+
+/// Slots in the ring unless `--uniform-ring` says otherwise: one being
+/// written, and up to two frames in flight reading.
+pub const DEFAULT_SLOTS: usize = 3;
+
+pub struct UniformRing {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    stride: wgpu::BufferAddress,
+    slots: usize,
+    current: usize,
+}
+
+impl UniformRing {
+    /// A ring of `slots` slots holding `size` bytes each.
+    pub fn new(device: &wgpu::Device, label: &str, size: usize, slots: usize) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let size = size as wgpu::BufferAddress;
+        let stride = size.next_multiple_of(alignment);
+        let slots = slots.max(1);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: stride * slots as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self { buffer, size, stride, slots, current: 0 }
+    }
+
+    /// Binding for a layout entry with `has_dynamic_offset`; pick the slot
+    /// with `offset()` when setting the bind group.
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(self.size),
+        })
+    }
+
+    /// Moves on to the next slot and writes `contents` into it.
+    pub fn write_next(&mut self, queue: &wgpu::Queue, contents: &[u8]) {
+        self.current = next_slot(self.current, self.slots);
+        queue.write_buffer(&self.buffer, self.offset() as wgpu::BufferAddress, contents);
+    }
+
+    /// Dynamic offset of the slot written last.
+    pub fn offset(&self) -> wgpu::DynamicOffset {
+        (self.stride * self.current as wgpu::BufferAddress) as wgpu::DynamicOffset
+    }
+}
+
+fn next_slot(current: usize, slots: usize) -> usize {
+    (current + 1) % slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_cycle_through_the_ring() {
+        let visited: Vec<usize> = std::iter::successors(Some(0), |&slot| Some(next_slot(slot, 3))).take(7).collect();
+        assert_eq!(visited, [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(next_slot(0, 1), 0);
+    }
+}
@@ -0,0 +1,106 @@
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub uv: [f32; 2],
+    pub normal: [f32; 3],
+}
+
+impl Vertex {
+    pub fn layout() -> VertexLayout {
+        VertexLayout::from_attributes(&[
+            (wgpu::VertexFormat::Float32x3, 0),
+            (wgpu::VertexFormat::Float32x3, 1),
+            (wgpu::VertexFormat::Float32x2, 2),
+            (wgpu::VertexFormat::Float32x3, 3),
+        ])
+    }
+}
+
+/// Describes an interleaved vertex buffer. Attributes are packed in the order
+/// they are added; offsets and the stride are computed from their formats.
+#[derive(Clone, Debug, Default)]
+pub struct VertexLayout {
+    attributes: Vec<wgpu::VertexAttribute>,
+    stride: wgpu::BufferAddress,
+}
+
+impl VertexLayout {
+    pub fn from_attributes(attributes: &[(wgpu::VertexFormat, u32)]) -> Self {
+        attributes
+            .iter()
+            .fold(Self::default(), |layout, &(format, location)| layout.attribute(format, location))
+    }
+
+    pub fn attribute(mut self, format: wgpu::VertexFormat, shader_location: u32) -> Self {
+        // wgpu requires each offset to be aligned to min(4, attribute size)
+        let align = format.size().min(4);
+        let offset = self.end().next_multiple_of(align);
+        self.attributes.push(wgpu::VertexAttribute { format, offset, shader_location });
+        self.stride = (offset + format.size()).next_multiple_of(wgpu::VERTEX_STRIDE_ALIGNMENT);
+        self
+    }
+
+    pub fn buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.attributes,
+        }
+    }
+
+    fn end(&self) -> wgpu::BufferAddress {
+        self.attributes.last().map_or(0, |a| a.offset + a.format.size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::VertexFormat;
+
+    fn offsets(layout: &VertexLayout) -> Vec<wgpu::BufferAddress> {
+        layout.attributes.iter().map(|a| a.offset).collect()
+    }
+
+    #[test]
+    fn default_vertex_layout_matches_struct() {
+        let layout = Vertex::layout();
+        assert_eq!(layout.stride, std::mem::size_of::<Vertex>() as wgpu::BufferAddress);
+        assert_eq!(offsets(&layout), [0, 12, 24, 32]);
+    }
+
+    #[test]
+    fn position_only() {
+        let layout = VertexLayout::from_attributes(&[(VertexFormat::Float32x3, 0)]);
+        assert_eq!(layout.stride, 12);
+        assert_eq!(offsets(&layout), [0]);
+    }
+
+    #[test]
+    fn position_uv_normal_tangent() {
+        let layout = VertexLayout::from_attributes(&[
+            (VertexFormat::Float32x3, 0),
+            (VertexFormat::Float32x2, 1),
+            (VertexFormat::Float32x3, 2),
+            (VertexFormat::Float32x4, 3),
+        ]);
+        assert_eq!(layout.stride, 48);
+        assert_eq!(offsets(&layout), [0, 12, 20, 32]);
+        let locations: Vec<u32> = layout.attributes.iter().map(|a| a.shader_location).collect();
+        assert_eq!(locations, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn small_attributes_are_aligned() {
+        let layout = VertexLayout::from_attributes(&[
+            (VertexFormat::Uint8x2, 0),
+            (VertexFormat::Float32, 1),
+            (VertexFormat::Unorm8x2, 2),
+        ]);
+        assert_eq!(offsets(&layout), [0, 4, 8]);
+        // Stride is padded up to wgpu's 4-byte alignment
+        assert_eq!(layout.stride, 12);
+    }
+}